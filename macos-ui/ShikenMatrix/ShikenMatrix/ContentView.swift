@@ -383,6 +383,11 @@ struct ContentView: View {
         RustBridge.setMediaCallback { m in
             DispatchQueue.main.async { [self] in self.currentMedia = m }
         }
+        RustBridge.setCommandCallback { c in
+            DispatchQueue.main.async { [self] in
+                self.addLog("服务器指令 \(c.command) (\(c.endpoint)): \(c.ok ? "已执行" : "已拒绝")", level: c.ok ? .info : .warning)
+            }
+        }
     }
 
     private func toggleReporter() {
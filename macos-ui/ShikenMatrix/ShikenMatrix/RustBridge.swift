@@ -30,6 +30,15 @@ func sm_reporter_stop(_ handle: UnsafeMutableRawPointer) -> Bool
 @_silgen_name("sm_reporter_get_status")
 func sm_reporter_get_status(_ handle: UnsafeRawPointer) -> SmStatus
 
+@_silgen_name("sm_reporter_get_endpoint_count")
+func sm_reporter_get_endpoint_count() -> Int
+
+@_silgen_name("sm_reporter_get_endpoint_status")
+func sm_reporter_get_endpoint_status(_ index: Int) -> UnsafeMutablePointer<SmEndpointStatus>?
+
+@_silgen_name("sm_endpoint_status_free")
+func sm_endpoint_status_free(_ status: UnsafeMutablePointer<SmEndpointStatus>)
+
 @_silgen_name("sm_reporter_is_running")
 func sm_reporter_is_running() -> Bool
 
@@ -42,6 +51,9 @@ func sm_reporter_set_window_callback(_ callback: @convention(c) (UnsafePointer<C
 @_silgen_name("sm_reporter_set_media_callback")
 func sm_reporter_set_media_callback(_ callback: @convention(c) (UnsafePointer<CChar>, UnsafePointer<CChar>, UnsafePointer<CChar>, Double, Double, Bool, UnsafePointer<UInt8>?, Int, UInt) -> Void, _ userData: UInt)
 
+@_silgen_name("sm_reporter_set_command_callback")
+func sm_reporter_set_command_callback(_ callback: @convention(c) (UnsafePointer<CChar>, UnsafePointer<CChar>, UnsafePointer<CChar>, UnsafePointer<CChar>, Bool, UInt) -> Void, _ userData: UInt)
+
 @_silgen_name("sm_check_accessibility_permission")
 func sm_check_accessibility_permission() -> Bool
 
@@ -76,6 +88,25 @@ struct SmStatus {
     var isRunning: Bool
     var isConnected: Bool
     var lastError: UnsafeMutablePointer<CChar>
+    var reconnectAttempt: UInt32
+    var nextRetryMs: UInt64
+    var hasLatency: Bool
+    var latencyMs: UInt32
+    var bytesSaved: UInt64
+}
+
+/// C-compatible struct for one endpoint's status
+struct SmEndpointStatus {
+    var name: UnsafeMutablePointer<CChar>
+    var enabled: Bool
+    var isConnected: Bool
+    var reconnectAttempt: UInt32
+    var nextRetryMs: UInt64
+    var hasLatency: Bool
+    var latencyMs: UInt32
+    var compressed: Bool
+    var bytesSaved: UInt64
+    var paused: Bool
 }
 
 // MARK: - Swift Models
@@ -93,6 +124,33 @@ struct ReporterStatus {
     var isRunning: Bool
     var isConnected: Bool
     var lastError: String?
+    var reconnectAttempt: UInt32
+    /// Next reconnect time, nil if none is scheduled
+    var nextRetry: Date?
+    /// Keepalive round-trip time in milliseconds, nil until measured
+    var latencyMs: UInt32?
+    /// Bytes permessage-deflate kept off the wire, all endpoints
+    var bytesSaved: UInt64
+}
+
+/// Swift model for one reporting endpoint
+struct EndpointStatus: Identifiable {
+    var name: String
+    var enabled: Bool
+    var isConnected: Bool
+    var reconnectAttempt: UInt32
+    /// Next reconnect time, nil if none is scheduled
+    var nextRetry: Date?
+    /// Keepalive round-trip time in milliseconds, nil until measured
+    var latencyMs: UInt32?
+    /// Whether the current connection uses permessage-deflate
+    var compressed: Bool
+    /// Bytes permessage-deflate kept off the wire
+    var bytesSaved: UInt64
+    /// Whether the server paused reporting on the current connection
+    var paused: Bool
+
+    var id: String { name }
 }
 
 /// Window data from backend
@@ -114,6 +172,16 @@ struct MediaData {
     var artworkData: Data?
 }
 
+/// Command pushed by a reporting server, already handled by the backend
+struct ServerCommandData {
+    var endpoint: String
+    var id: String
+    var command: String
+    /// Command arguments as a JSON object
+    var argsJson: String
+    var ok: Bool
+}
+
 // MARK: - Rust Bridge
 
 /// Bridge to Rust library
@@ -122,6 +190,7 @@ class RustBridge {
     fileprivate static var logCallback: ((SmLogLevel, String) -> Void)?
     fileprivate static var windowCallback: ((WindowData) -> Void)?
     fileprivate static var mediaCallback: ((MediaData) -> Void)?
+    fileprivate static var commandCallback: ((ServerCommandData) -> Void)?
     
     /// Set log callback to receive formatted logs from backend
     static func setLogCallback(_ callback: @escaping (SmLogLevel, String) -> Void) {
@@ -147,16 +216,26 @@ class RustBridge {
         print("✅ RustBridge: Media callback set")
     }
 
+    /// Set command callback to receive commands pushed by reporting servers
+    static func setCommandCallback(_ callback: @escaping (ServerCommandData) -> Void) {
+        print("🔧 RustBridge: Setting command callback")
+        commandCallback = callback
+        sm_reporter_set_command_callback(commandCallbackWrapper, 0)
+        print("✅ RustBridge: Command callback set")
+    }
+
     /// Clear all callbacks to prevent memory leaks
     static func clearCallbacks() {
         print("🧹 RustBridge: Clearing all callbacks...")
         logCallback = nil
         windowCallback = nil
         mediaCallback = nil
+        commandCallback = nil
         // Set dummy C callbacks to prevent crashes from dangling pointers
         sm_reporter_set_log_callback({ _, _, _ in }, 0)
         sm_reporter_set_window_callback({ _, _, _, _, _, _ in }, 0)
         sm_reporter_set_media_callback({ _, _, _, _, _, _, _, _, _ in }, 0)
+        sm_reporter_set_command_callback({ _, _, _, _, _, _ in }, 0)
         print("✅ RustBridge: All callbacks cleared")
     }
 
@@ -255,10 +334,34 @@ class RustBridge {
         return ReporterStatus(
             isRunning: status.isRunning,
             isConnected: status.isConnected,
-            lastError: lastError
+            lastError: lastError,
+            reconnectAttempt: status.reconnectAttempt,
+            nextRetry: status.nextRetryMs == 0 ? nil : Date(timeIntervalSince1970: Double(status.nextRetryMs) / 1000),
+            latencyMs: status.hasLatency ? status.latencyMs : nil,
+            bytesSaved: status.bytesSaved
         )
     }
 
+    /// Get the status of every endpoint, the default endpoint first
+    static func getEndpointStatuses() -> [EndpointStatus] {
+        (0..<sm_reporter_get_endpoint_count()).compactMap { index in
+            guard let ptr = sm_reporter_get_endpoint_status(index) else { return nil }
+            defer { sm_endpoint_status_free(ptr) }
+            let status = ptr.pointee
+            return EndpointStatus(
+                name: String(cString: status.name),
+                enabled: status.enabled,
+                isConnected: status.isConnected,
+                reconnectAttempt: status.reconnectAttempt,
+                nextRetry: status.nextRetryMs == 0 ? nil : Date(timeIntervalSince1970: Double(status.nextRetryMs) / 1000),
+                latencyMs: status.hasLatency ? status.latencyMs : nil,
+                compressed: status.compressed,
+                bytesSaved: status.bytesSaved,
+                paused: status.paused
+            )
+        }
+    }
+
     /// Check if reporter is running
     static func isRunning() -> Bool {
         return sm_reporter_is_running()
@@ -340,3 +443,18 @@ private func mediaCallbackWrapper(title: UnsafePointer<CChar>, artist: UnsafePoi
         RustBridge.mediaCallback?(data)
     }
 }
+
+/// C callback wrapper for server commands
+private func commandCallbackWrapper(endpoint: UnsafePointer<CChar>, id: UnsafePointer<CChar>, command: UnsafePointer<CChar>, argsJson: UnsafePointer<CChar>, ok: Bool, _: UInt) {
+    let data = ServerCommandData(
+        endpoint: String(cString: endpoint),
+        id: String(cString: id),
+        command: String(cString: command),
+        argsJson: String(cString: argsJson),
+        ok: ok
+    )
+    print("🔔 commandCallbackWrapper called: \(data.command) (\(data.id)) from \(data.endpoint), ok: \(ok)")
+    DispatchQueue.main.async {
+        RustBridge.commandCallback?(data)
+    }
+}
@@ -10,6 +10,16 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Interval of the window/media polling loop until a server changes it
+ */
+#define DEFAULT_POLL_INTERVAL_MS 1000
+
+/**
+ * Version of the message format spoken by this client
+ */
+#define PROTOCOL_VERSION 1
+
 /**
  * Log level for callback
  */
@@ -64,8 +74,74 @@ typedef struct SmStatus {
    * Last error message (null-terminated string, owned by Rust, null if no error)
    */
   char *last_error;
+  /**
+   * Failed reconnect attempts since the last stable connection
+   */
+  uint32_t reconnect_attempt;
+  /**
+   * Unix time in milliseconds of the next reconnect attempt (0 if none is scheduled)
+   */
+  uint64_t next_retry_ms;
+  /**
+   * Whether a keepalive round-trip has been measured on the current connection
+   */
+  bool has_latency;
+  /**
+   * Last keepalive round-trip time in milliseconds (0 if has_latency is false)
+   */
+  uint32_t latency_ms;
+  /**
+   * Bytes permessage-deflate saved across all endpoints since start
+   */
+  uint64_t bytes_saved;
 } SmStatus;
 
+/**
+ * Status of one reporting endpoint
+ */
+typedef struct SmEndpointStatus {
+  /**
+   * Endpoint name (null-terminated string, owned by Rust)
+   */
+  char *name;
+  /**
+   * Whether the endpoint is configured to connect
+   */
+  bool enabled;
+  /**
+   * Whether the WebSocket is connected
+   */
+  bool is_connected;
+  /**
+   * Failed reconnect attempts since the last stable connection
+   */
+  uint32_t reconnect_attempt;
+  /**
+   * Unix time in milliseconds of the next reconnect attempt (0 if none is scheduled)
+   */
+  uint64_t next_retry_ms;
+  /**
+   * Whether a keepalive round-trip has been measured on the current connection
+   */
+  bool has_latency;
+  /**
+   * Last keepalive round-trip time in milliseconds (0 if has_latency is false)
+   */
+  uint32_t latency_ms;
+  /**
+   * Whether the current connection uses permessage-deflate
+   */
+  bool compressed;
+  /**
+   * Bytes permessage-deflate saved on this endpoint since start
+   */
+  uint64_t bytes_saved;
+  /**
+   * Whether the server paused reporting on the current connection
+   */
+  bool paused;
+} SmEndpointStatus;
+
 /**
  * Callback function type for logs
  */
@@ -94,6 +170,16 @@ typedef void (*SmMediaDataCallback)(const char *title,
                                     uintptr_t artwork_size,
                                     uintptr_t user_data);
 
+/**
+ * Callback function type for server commands, called after the command was handled
+ */
+typedef void (*SmCommandCallback)(const char *endpoint,
+                                  const char *id,
+                                  const char *command,
+                                  const char *args_json,
+                                  bool ok,
+                                  uintptr_t user_data);
+
 /**
  * Check if accessibility permission is granted
  *
@@ -188,6 +274,8 @@ struct SmReporter *sm_reporter_start(const struct SmConfig *config);
 /**
  * Stop the running reporter
  *
+ * Blocks until its connections and monitor thread have exited, at most a few seconds.
+ *
  * # Arguments
  * * `handle` - Handle returned by sm_reporter_start
  *
@@ -208,6 +296,35 @@ bool sm_reporter_stop(struct SmReporter *_handle);
  */
 struct SmStatus sm_reporter_get_status(const struct SmReporter *_handle);
 
+/**
+ * Get the number of configured endpoints (0 if the reporter is not running)
+ */
+uintptr_t sm_reporter_get_endpoint_count(void);
+
+/**
+ * Get the status of one endpoint
+ *
+ * # Arguments
+ * * `index` - Endpoint index, 0 is the default endpoint
+ *
+ * # Returns
+ * * Non-null pointer - SmEndpointStatus that must be freed with sm_endpoint_status_free
+ * * Null pointer - Reporter not running or index out of range
+ */
+struct SmEndpointStatus *sm_reporter_get_endpoint_status(uintptr_t index);
+
+/**
+ * Free a SmEndpointStatus returned by sm_reporter_get_endpoint_status
+ *
+ * # Arguments
+ * * `status` - Pointer to free (safe if null)
+ *
+ * # Safety
+ * `status` must be null or a pointer returned by sm_reporter_get_endpoint_status that
+ * hasn't been freed yet; it must not be used afterwards
+ */
+void sm_endpoint_status_free(struct SmEndpointStatus *status);
+
 /**
  * Check if the reporter is currently running
  *
@@ -244,6 +361,17 @@ void sm_reporter_set_window_callback(SmWindowDataCallback callback, uintptr_t us
  */
 void sm_reporter_set_media_callback(SmMediaDataCallback callback, uintptr_t user_data);
 
+/**
+ * Set command callback for commands pushed by reporting servers
+ *
+ * Called after the reporter handled and answered the command; `args_json` holds its arguments.
+ *
+ * # Arguments
+ * * `callback` - Function pointer to command callback
+ * * `user_data` - User data value to pass to callback
+ */
+void sm_reporter_set_command_callback(SmCommandCallback callback, uintptr_t user_data);
+
 extern bool AXIsProcessTrusted(void);
 
 extern bool AXIsProcessTrustedWithOptions(const __CFDictionary *options);
@@ -49,13 +49,15 @@ pub extern "C" fn sm_config_save(config: *const SmConfig) -> bool {
     };
 
     tracing::info!("Saving config: enabled={}, ws_url={}, token_len={}", 
-        enabled, ws_url, token.len());
+        enabled, crate::services::auth::redact_url_str(&ws_url), token.len());
 
+    // Keep settings that are only configurable in config.toml
     let reporter_config = ReporterConfig {
         enabled,
         ws_url,
         token,
         enable_media_reporting,
+        ..load_config().reporter
     };
 
     match save_reporter_config(&reporter_config) {
@@ -1,8 +1,9 @@
 //! FFI functions for reporter lifecycle management
 
-use super::types::{SmConfig, SmReporter, SmStatus, SmLogCallback, SmWindowDataCallback, SmMediaDataCallback};
+use super::types::{SmConfig, SmEndpointStatus, SmReporter, SmStatus, SmLogCallback, SmWindowDataCallback, SmMediaDataCallback, SmCommandCallback};
 use crate::services::Reporter;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::time::Duration;
 use std::sync::{Arc, Mutex, OnceLock};
 use tracing::{info, error};
 use tokio::runtime::Runtime;
@@ -13,6 +14,9 @@ static GLOBAL_REPORTER: Mutex<Option<ReporterHandle>> = Mutex::new(None);
 /// Global tokio runtime (using OnceLock for safe initialization)
 static GLOBAL_RUNTIME: OnceLock<Runtime> = OnceLock::new();
 
+/// How long sm_reporter_stop waits for the reporter's tasks and monitor thread
+const STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
 /// Global logging initialized flag
 static LOGGING_INITIALIZED: OnceLock<()> = OnceLock::new();
 
@@ -90,23 +94,25 @@ pub extern "C" fn sm_reporter_start(config: *const SmConfig) -> *mut SmReporter
         info!(">>> Media reporting DISABLED");
     }
 
+    // Settings not exposed through SmConfig come from config.toml
     let reporter_config = crate::services::ReporterConfig {
         enabled,
         ws_url: ws_url.clone(),
         token: token.clone(),
         enable_media_reporting,
+        ..crate::services::load_config().reporter
     };
 
     info!(">>> Creating reporter with config:");
     info!(">>>   enabled: {}", enabled);
-    info!(">>>   ws_url: {}", ws_url);
+    info!(">>>   ws_url: {}", crate::services::auth::redact_url_str(&ws_url));
     info!(">>>   token length: {}", token.len());
     info!(">>>   enable_media_reporting: {}", enable_media_reporting);
 
     // Create reporter using the runtime handle
     let rt = get_runtime();
     let handle = rt.handle().clone();
-    let reporter = Reporter::new_with_handle(reporter_config, handle);
+    let reporter = Reporter::new_with_handle(reporter_config, crate::platform::default_provider(), handle);
 
     // Store the reporter globally
     {
@@ -123,6 +129,8 @@ pub extern "C" fn sm_reporter_start(config: *const SmConfig) -> *mut SmReporter
 
 /// Stop the running reporter
 ///
+/// Blocks until its connections and monitor thread have exited, at most a few seconds.
+///
 /// # Arguments
 /// * `handle` - Handle returned by sm_reporter_start
 ///
@@ -132,15 +140,17 @@ pub extern "C" fn sm_reporter_start(config: *const SmConfig) -> *mut SmReporter
 #[no_mangle]
 pub extern "C" fn sm_reporter_stop(_handle: *mut SmReporter) -> bool {
     // We ignore the actual handle value and just check if a reporter is running
-    let mut guard = GLOBAL_REPORTER.lock().unwrap();
-    if guard.is_some() {
-        *guard = None;
-        info!("Reporter stopped successfully");
-        true
-    } else {
+    // The lock is released before waiting, callbacks may call back into the FFI
+    let reporter = GLOBAL_REPORTER.lock().unwrap().take();
+    let Some(reporter) = reporter else {
         error!("sm_reporter_stop: no reporter running");
-        false
-    }
+        return false;
+    };
+
+    // Wait for the old tasks so a following start doesn't share queue and cache files with them
+    reporter.stop(STOP_TIMEOUT);
+    info!("Reporter stopped successfully");
+    true
 }
 
 /// Get the current status of the reporter
@@ -157,11 +167,79 @@ pub extern "C" fn sm_reporter_get_status(_handle: *const SmReporter) -> SmStatus
     let is_running = guard.is_some();
     // Get actual WebSocket connection status from the reporter
     let is_connected = guard.as_ref().map(|r| r.is_connected()).unwrap_or(false);
+    let reconnect = guard.as_ref().map(|r| r.reconnect_status()).unwrap_or_default();
+    let latency = guard.as_ref().and_then(|r| r.latency());
+    let bytes_saved = guard.as_ref().map(|r| r.bytes_saved()).unwrap_or(0);
 
     SmStatus {
         is_running,
         is_connected,
         last_error: std::ptr::null_mut(),
+        reconnect_attempt: reconnect.attempt,
+        next_retry_ms: reconnect.next_retry_at.unwrap_or(0),
+        has_latency: latency.is_some(),
+        latency_ms: latency_millis(latency),
+        bytes_saved,
+    }
+}
+
+fn latency_millis(latency: Option<Duration>) -> u32 {
+    latency.map(|l| l.as_millis().min(u32::MAX as u128) as u32).unwrap_or(0)
+}
+
+/// Get the number of configured endpoints (0 if the reporter is not running)
+#[no_mangle]
+pub extern "C" fn sm_reporter_get_endpoint_count() -> usize {
+    let guard = GLOBAL_REPORTER.lock().unwrap();
+    guard.as_ref().map(|r| r.endpoint_statuses().len()).unwrap_or(0)
+}
+
+/// Get the status of one endpoint
+///
+/// # Arguments
+/// * `index` - Endpoint index, 0 is the default endpoint
+///
+/// # Returns
+/// * Non-null pointer - SmEndpointStatus that must be freed with sm_endpoint_status_free
+/// * Null pointer - Reporter not running or index out of range
+#[no_mangle]
+pub extern "C" fn sm_reporter_get_endpoint_status(index: usize) -> *mut SmEndpointStatus {
+    let guard = GLOBAL_REPORTER.lock().unwrap();
+    let Some(status) = guard.as_ref().and_then(|r| r.endpoint_statuses().into_iter().nth(index)) else {
+        return std::ptr::null_mut();
+    };
+
+    Box::into_raw(Box::new(SmEndpointStatus {
+        name: CString::new(status.name).unwrap_or_default().into_raw(),
+        enabled: status.enabled,
+        is_connected: status.connected,
+        reconnect_attempt: status.reconnect.attempt,
+        next_retry_ms: status.reconnect.next_retry_at.unwrap_or(0),
+        has_latency: status.latency.is_some(),
+        latency_ms: latency_millis(status.latency),
+        compressed: status.compressed,
+        bytes_saved: status.bytes_saved,
+        paused: status.paused,
+    }))
+}
+
+/// Free a SmEndpointStatus returned by sm_reporter_get_endpoint_status
+///
+/// # Arguments
+/// * `status` - Pointer to free (safe if null)
+///
+/// # Safety
+/// `status` must be null or a pointer returned by sm_reporter_get_endpoint_status that
+/// hasn't been freed yet; it must not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn sm_endpoint_status_free(status: *mut SmEndpointStatus) {
+    if status.is_null() {
+        return;
+    }
+
+    let status = Box::from_raw(status);
+    if !status.name.is_null() {
+        let _ = CString::from_raw(status.name);
     }
 }
 
@@ -227,5 +305,23 @@ pub extern "C" fn sm_reporter_set_media_callback(callback: SmMediaDataCallback,
     }
 }
 
+/// Set command callback for commands pushed by reporting servers
+///
+/// Called after the reporter handled and answered the command; `args_json` holds its arguments.
+///
+/// # Arguments
+/// * `callback` - Function pointer to command callback
+/// * `user_data` - User data value to pass to callback
+#[no_mangle]
+pub extern "C" fn sm_reporter_set_command_callback(callback: SmCommandCallback, user_data: usize) {
+    let guard = GLOBAL_REPORTER.lock().unwrap();
+    if let Some(reporter) = guard.as_ref() {
+        reporter.set_command_callback(Some(callback), user_data);
+        info!("Command callback registered");
+    } else {
+        error!("sm_reporter_set_command_callback: no reporter running");
+    }
+}
+
 // Note: We don't implement sm_reporter_free since the handle is just a token
 // and the actual cleanup happens in sm_reporter_stop
@@ -24,6 +24,41 @@ pub struct SmStatus {
     pub is_connected: bool,
     /// Last error message (null-terminated string, owned by Rust, null if no error)
     pub last_error: *mut c_char,
+    /// Failed reconnect attempts since the last stable connection
+    pub reconnect_attempt: u32,
+    /// Unix time in milliseconds of the next reconnect attempt (0 if none is scheduled)
+    pub next_retry_ms: u64,
+    /// Whether a keepalive round-trip has been measured on the current connection
+    pub has_latency: bool,
+    /// Last keepalive round-trip time in milliseconds (0 if has_latency is false)
+    pub latency_ms: u32,
+    /// Bytes permessage-deflate saved across all endpoints since start
+    pub bytes_saved: u64,
+}
+
+/// Status of one reporting endpoint
+#[repr(C)]
+pub struct SmEndpointStatus {
+    /// Endpoint name (null-terminated string, owned by Rust)
+    pub name: *mut c_char,
+    /// Whether the endpoint is configured to connect
+    pub enabled: bool,
+    /// Whether the WebSocket is connected
+    pub is_connected: bool,
+    /// Failed reconnect attempts since the last stable connection
+    pub reconnect_attempt: u32,
+    /// Unix time in milliseconds of the next reconnect attempt (0 if none is scheduled)
+    pub next_retry_ms: u64,
+    /// Whether a keepalive round-trip has been measured on the current connection
+    pub has_latency: bool,
+    /// Last keepalive round-trip time in milliseconds (0 if has_latency is false)
+    pub latency_ms: u32,
+    /// Whether the current connection uses permessage-deflate
+    pub compressed: bool,
+    /// Bytes permessage-deflate saved on this endpoint since start
+    pub bytes_saved: u64,
+    /// Whether the server paused reporting on the current connection
+    pub paused: bool,
 }
 
 /// Window information for FFI
@@ -86,3 +121,13 @@ pub type SmMediaDataCallback = extern "C" fn(
     artwork_size: usize,
     user_data: usize
 );
+
+/// Callback function type for server commands, called after the command was handled
+pub type SmCommandCallback = extern "C" fn(
+    endpoint: *const c_char,
+    id: *const c_char,
+    command: *const c_char,
+    args_json: *const c_char,
+    ok: bool,
+    user_data: usize
+);
@@ -14,11 +14,13 @@
 //! ## Usage as a Library
 //!
 //! ```rust
+//! use shikenmatrix::platform::default_provider;
 //! use shikenmatrix::services::{Reporter, ReporterConfig, load_config};
 //!
 //! let config = load_config();
 //! if config.reporter.enabled {
-//!     let reporter = Reporter::new(config.reporter);
+//!     // Any `Box<dyn PlatformProvider>` works here, e.g. a specific backend or a mock
+//!     let reporter = Reporter::new(config.reporter, default_provider());
 //!     // Use reporter...
 //! }
 //! ```
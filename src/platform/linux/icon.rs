@@ -0,0 +1,630 @@
+//! Linux 应用图标获取模块
+//!
+//! 根据 WM_CLASS / Wayland app_id 找到应用的 .desktop 文件，
+//! 再按 freedesktop Icon Theme Specification 解析 `Icon=` 对应的图标文件，
+//! 最终渲染为 32x32 PNG（与 macOS 保持一致）
+
+use resvg::tiny_skia::{FilterQuality, Pixmap, PixmapPaint, Transform};
+use resvg::usvg;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// 图标目标尺寸
+const ICON_SIZE: u32 = 32;
+
+/// 按查找优先级排列的图标扩展名（不支持 xpm）
+const ICON_EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+/// 所有主题最终回退到的主题
+const FALLBACK_THEME: &str = "hicolor";
+
+/// 图标缓存：app id -> PNG 数据（找不到图标也缓存，避免每次扫描文件系统）
+static ICON_CACHE: Mutex<Option<HashMap<String, Option<Vec<u8>>>>> = Mutex::new(None);
+
+/// .desktop 文件索引，首次查找时扫描一次
+static DESKTOP_INDEX: OnceLock<DesktopIndex> = OnceLock::new();
+
+/// 带缓存的图标获取
+pub fn get_cached_app_icon(app_id: &str) -> Option<Vec<u8>> {
+    if app_id.is_empty() {
+        return None;
+    }
+
+    // 检查缓存
+    {
+        let cache = ICON_CACHE.lock().ok()?;
+        if let Some(ref map) = *cache {
+            if let Some(data) = map.get(app_id) {
+                return data.clone();
+            }
+        }
+    }
+
+    let icon_data = load_app_icon(app_id);
+
+    // 存入缓存
+    {
+        let mut cache = ICON_CACHE.lock().ok()?;
+        let map = cache.get_or_insert_with(HashMap::new);
+        map.insert(app_id.to_string(), icon_data.clone());
+    }
+
+    icon_data
+}
+
+/// 解析 app id 对应的图标并渲染为 PNG
+fn load_app_icon(app_id: &str) -> Option<Vec<u8>> {
+    // 没有 .desktop 文件时，直接把 app id 当作图标名尝试（很多程序以可执行文件名安装图标）
+    let index = DESKTOP_INDEX.get_or_init(|| DesktopIndex::build(&xdg_data_dirs()));
+    let icon_name = index
+        .find(app_id)
+        .and_then(|path| read_desktop_icon(&path))
+        .unwrap_or_else(|| app_id.to_lowercase());
+
+    let icon_path = if Path::new(&icon_name).is_absolute() {
+        PathBuf::from(&icon_name)
+    } else {
+        find_icon(&icon_name, ICON_SIZE, current_icon_theme().as_deref(), &icon_base_dirs())?
+    };
+
+    render_icon_png(&icon_path)
+}
+
+/// $XDG_DATA_HOME 与 $XDG_DATA_DIRS（按优先级排列）
+fn xdg_data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    match std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(data_home) => dirs.push(PathBuf::from(data_home)),
+        None => {
+            if let Some(home) = dirs::home_dir() {
+                dirs.push(home.join(".local/share"));
+            }
+        }
+    }
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+
+    dirs
+}
+
+/// 图标主题的基础目录：$HOME/.icons、$XDG_DATA_DIRS/icons、/usr/share/pixmaps
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".icons"));
+    }
+    dirs.extend(xdg_data_dirs().into_iter().map(|d| d.join("icons")));
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    dirs
+}
+
+type IniSections = HashMap<String, HashMap<String, String>>;
+
+/// 简单的 INI 解析（.desktop 与 index.theme 共用）
+fn parse_ini(content: &str) -> IniSections {
+    let mut sections: IniSections = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name.to_string());
+            continue;
+        }
+        if let (Some(section), Some((key, value))) = (current.as_ref(), line.split_once('=')) {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    sections
+}
+
+/// applications 目录下的 .desktop 文件及其 `StartupWMClass`
+struct DesktopIndex {
+    /// 按 $XDG_DATA_DIRS 优先级排列
+    files: Vec<PathBuf>,
+    /// 小写的 StartupWMClass -> 第一个声明它的文件
+    wm_classes: HashMap<String, PathBuf>,
+}
+
+impl DesktopIndex {
+    /// 扫描各数据目录的 applications（含一级子目录）
+    fn build(data_dirs: &[PathBuf]) -> Self {
+        let mut files = Vec::new();
+        for dir in data_dirs.iter().map(|d| d.join("applications")) {
+            collect_desktop_files(&dir, 1, &mut files);
+        }
+
+        let mut wm_classes = HashMap::new();
+        for path in &files {
+            let wm_class = std::fs::read_to_string(path)
+                .ok()
+                .map(|content| parse_ini(&content))
+                .and_then(|ini| ini.get("Desktop Entry")?.get("StartupWMClass").cloned())
+                .filter(|wm_class| !wm_class.is_empty());
+            if let Some(wm_class) = wm_class {
+                wm_classes.entry(wm_class.to_lowercase()).or_insert_with(|| path.clone());
+            }
+        }
+
+        Self { files, wm_classes }
+    }
+
+    /// 根据 app id 查找 .desktop 文件
+    ///
+    /// 匹配顺序：文件名完全一致 → 文件名忽略大小写 → `StartupWMClass` →
+    /// 反向域名形式的文件名后缀（如 `org.gnome.Nautilus` 对应 `nautilus`）
+    fn find(&self, app_id: &str) -> Option<PathBuf> {
+        let stem = |path: &PathBuf| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+
+        if let Some(path) = self.files.iter().find(|path| stem(path) == app_id) {
+            return Some(path.clone());
+        }
+        if let Some(path) = self.files.iter().find(|path| stem(path).eq_ignore_ascii_case(app_id)) {
+            return Some(path.clone());
+        }
+        if let Some(path) = self.wm_classes.get(&app_id.to_lowercase()) {
+            return Some(path.clone());
+        }
+
+        let suffix = format!(".{}", app_id.to_lowercase());
+        self.files
+            .iter()
+            .find(|path| stem(path).to_lowercase().ends_with(&suffix))
+            .cloned()
+    }
+}
+
+fn collect_desktop_files(dir: &Path, depth: u32, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                collect_desktop_files(&path, depth - 1, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "desktop") {
+            files.push(path);
+        }
+    }
+}
+
+/// 读取 .desktop 文件的 Icon= 键
+fn read_desktop_icon(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    parse_ini(&content)
+        .get("Desktop Entry")?
+        .get("Icon")
+        .cloned()
+        .filter(|icon| !icon.is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DirType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// index.theme 中描述的一个子目录
+#[derive(Debug, Clone)]
+struct ThemeSubdir {
+    path: String,
+    size: u32,
+    scale: u32,
+    dir_type: DirType,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeSubdir {
+    fn parse(path: &str, keys: &HashMap<String, String>) -> Option<Self> {
+        let get = |key: &str| keys.get(key).and_then(|v| v.parse::<u32>().ok());
+        let size = get("Size")?;
+        let dir_type = match keys.get("Type").map(String::as_str) {
+            Some("Fixed") => DirType::Fixed,
+            Some("Scalable") => DirType::Scalable,
+            _ => DirType::Threshold,
+        };
+        Some(Self {
+            path: path.to_string(),
+            size,
+            scale: get("Scale").unwrap_or(1),
+            dir_type,
+            min_size: get("MinSize").unwrap_or(size),
+            max_size: get("MaxSize").unwrap_or(size),
+            threshold: get("Threshold").unwrap_or(2),
+        })
+    }
+
+    /// DirectoryMatchesSize（只考虑 scale = 1）
+    fn matches_size(&self, size: u32) -> bool {
+        if self.scale != 1 {
+            return false;
+        }
+        match self.dir_type {
+            DirType::Fixed => self.size == size,
+            DirType::Scalable => self.min_size <= size && size <= self.max_size,
+            DirType::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    /// DirectorySizeDistance（只考虑 scale = 1）
+    fn size_distance(&self, size: u32) -> u32 {
+        let (min, max) = match self.dir_type {
+            DirType::Fixed => (self.size * self.scale, self.size * self.scale),
+            DirType::Scalable => (self.min_size * self.scale, self.max_size * self.scale),
+            DirType::Threshold => (
+                self.size.saturating_sub(self.threshold) * self.scale,
+                (self.size + self.threshold) * self.scale,
+            ),
+        };
+        if size < min {
+            min - size
+        } else {
+            size.saturating_sub(max)
+        }
+    }
+}
+
+/// 已加载的图标主题
+struct IconTheme {
+    /// 各基础目录下存在的该主题目录
+    dirs: Vec<PathBuf>,
+    subdirs: Vec<ThemeSubdir>,
+    parents: Vec<String>,
+}
+
+impl IconTheme {
+    /// 加载主题：使用第一个找到的 index.theme
+    fn load(name: &str, base_dirs: &[PathBuf]) -> Option<Self> {
+        let dirs: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+
+        let index = dirs
+            .iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join("index.theme")).ok())?;
+        let ini = parse_ini(&index);
+        let theme = ini.get("Icon Theme")?;
+
+        let list = |key: &str| -> Vec<String> {
+            theme
+                .get(key)
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default()
+        };
+
+        let mut subdir_names = list("Directories");
+        subdir_names.extend(list("ScaledDirectories"));
+        let subdirs = subdir_names
+            .iter()
+            .filter_map(|subdir| ThemeSubdir::parse(subdir, ini.get(subdir)?))
+            .collect();
+
+        Some(Self {
+            dirs,
+            subdirs,
+            parents: list("Inherits"),
+        })
+    }
+
+    /// LookupIcon：先找尺寸匹配的目录，再找尺寸最接近的
+    fn lookup(&self, icon_name: &str, size: u32) -> Option<PathBuf> {
+        let candidate = |subdir: &ThemeSubdir| {
+            self.dirs.iter().find_map(|dir| {
+                ICON_EXTENSIONS.iter().find_map(|ext| {
+                    let path = dir.join(&subdir.path).join(format!("{}.{}", icon_name, ext));
+                    path.is_file().then_some(path)
+                })
+            })
+        };
+
+        if let Some(path) = self
+            .subdirs
+            .iter()
+            .filter(|subdir| subdir.matches_size(size))
+            .find_map(candidate)
+        {
+            return Some(path);
+        }
+
+        self.subdirs
+            .iter()
+            .filter_map(|subdir| Some((subdir.size_distance(size), candidate(subdir)?)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, path)| path)
+    }
+}
+
+/// 当前用户选择的图标主题（GTK / KDE 设置）
+fn current_icon_theme() -> Option<String> {
+    let config_dir = dirs::config_dir()?;
+
+    for settings in ["gtk-4.0/settings.ini", "gtk-3.0/settings.ini"] {
+        let theme = std::fs::read_to_string(config_dir.join(settings))
+            .ok()
+            .and_then(|content| parse_ini(&content).get("Settings")?.get("gtk-icon-theme-name").cloned());
+        if let Some(theme) = theme.filter(|t| !t.is_empty()) {
+            return Some(theme.trim_matches('"').to_string());
+        }
+    }
+
+    std::fs::read_to_string(config_dir.join("kdeglobals"))
+        .ok()
+        .and_then(|content| parse_ini(&content).get("Icons")?.get("Theme").cloned())
+        .filter(|t| !t.is_empty())
+}
+
+/// FindIconHelper：在主题及其父主题中递归查找
+fn find_icon_in_theme(
+    icon_name: &str,
+    size: u32,
+    theme_name: &str,
+    base_dirs: &[PathBuf],
+    visited: &mut HashSet<String>,
+) -> Option<PathBuf> {
+    if !visited.insert(theme_name.to_string()) {
+        return None;
+    }
+    let theme = IconTheme::load(theme_name, base_dirs)?;
+    if let Some(path) = theme.lookup(icon_name, size) {
+        return Some(path);
+    }
+    theme
+        .parents
+        .iter()
+        .find_map(|parent| find_icon_in_theme(icon_name, size, parent, base_dirs, visited))
+}
+
+/// FindIcon：用户主题 → hicolor → 不属于任何主题的图标 (LookupFallbackIcon)
+fn find_icon(icon_name: &str, size: u32, user_theme: Option<&str>, base_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let mut visited = HashSet::new();
+
+    if let Some(theme) = user_theme {
+        if let Some(path) = find_icon_in_theme(icon_name, size, theme, base_dirs, &mut visited) {
+            return Some(path);
+        }
+    }
+    if let Some(path) = find_icon_in_theme(icon_name, size, FALLBACK_THEME, base_dirs, &mut visited) {
+        return Some(path);
+    }
+
+    base_dirs.iter().find_map(|dir| {
+        ICON_EXTENSIONS.iter().find_map(|ext| {
+            let path = dir.join(format!("{}.{}", icon_name, ext));
+            path.is_file().then_some(path)
+        })
+    })
+}
+
+/// 将 PNG / SVG 图标渲染为 32x32 PNG
+fn render_icon_png(path: &Path) -> Option<Vec<u8>> {
+    let data = std::fs::read(path).ok()?;
+    let mut target = Pixmap::new(ICON_SIZE, ICON_SIZE)?;
+
+    let is_svg = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+
+    if is_svg {
+        let tree = usvg::Tree::from_data(&data, &usvg::Options::default()).ok()?;
+        let size = tree.size();
+        resvg::render(&tree, fit_transform(size.width(), size.height()), &mut target.as_mut());
+    } else {
+        let source = Pixmap::decode_png(&data).ok()?;
+        let transform = fit_transform(source.width() as f32, source.height() as f32);
+        let paint = PixmapPaint {
+            quality: FilterQuality::Bicubic,
+            ..PixmapPaint::default()
+        };
+        target.draw_pixmap(0, 0, source.as_ref(), &paint, transform, None);
+    }
+
+    target.encode_png().ok()
+}
+
+/// 等比缩放并居中到目标尺寸
+fn fit_transform(width: f32, height: f32) -> Transform {
+    let target = ICON_SIZE as f32;
+    let scale = target / width.max(height).max(1.0);
+    let dx = (target - width * scale) / 2.0;
+    let dy = (target - height * scale) / 2.0;
+    Transform::from_scale(scale, scale).post_translate(dx, dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试结束时删除的临时目录
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("shikenmatrix-icon-{}-{:08x}", std::process::id(), fastrand::u32(..)));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        /// 写入文件，自动创建父目录
+        fn write(&self, path: &str, content: &str) -> PathBuf {
+            let path = self.0.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn subdir(keys: &[(&str, &str)]) -> ThemeSubdir {
+        let keys = keys.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        ThemeSubdir::parse("apps", &keys).unwrap()
+    }
+
+    fn theme(name: &str, inherits: &str, directories: &[(&str, &str)]) -> String {
+        let names: Vec<&str> = directories.iter().map(|(dir, _)| *dir).collect();
+        let mut index = format!("[Icon Theme]\nName={}\nInherits={}\nDirectories={}\n", name, inherits, names.join(","));
+        for (dir, keys) in directories {
+            index.push_str(&format!("\n[{}]\n{}\n", dir, keys));
+        }
+        index
+    }
+
+    #[test]
+    fn subdir_size_matching() {
+        let fixed = subdir(&[("Size", "32"), ("Type", "Fixed")]);
+        assert!(fixed.matches_size(32));
+        assert!(!fixed.matches_size(31));
+        assert_eq!(fixed.size_distance(24), 8);
+        assert_eq!(fixed.size_distance(48), 16);
+
+        let scalable = subdir(&[("Size", "48"), ("Type", "Scalable"), ("MinSize", "16"), ("MaxSize", "256")]);
+        assert!(scalable.matches_size(16) && scalable.matches_size(256));
+        assert!(!scalable.matches_size(8));
+        assert_eq!(scalable.size_distance(32), 0);
+        assert_eq!(scalable.size_distance(8), 8);
+        assert_eq!(scalable.size_distance(512), 256);
+
+        // 未指定 Type 时为 Threshold，默认阈值 2
+        let threshold = subdir(&[("Size", "32")]);
+        assert_eq!(threshold.dir_type, DirType::Threshold);
+        assert!(threshold.matches_size(30) && threshold.matches_size(34));
+        assert!(!threshold.matches_size(35));
+        assert_eq!(threshold.size_distance(24), 6);
+        assert_eq!(threshold.size_distance(40), 6);
+
+        // 只考虑 scale = 1
+        let scaled = subdir(&[("Size", "16"), ("Scale", "2"), ("Type", "Fixed")]);
+        assert!(!scaled.matches_size(16));
+        assert_eq!(scaled.size_distance(32), 0);
+
+        assert!(ThemeSubdir::parse("apps", &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn lookup_prefers_matching_then_closest_size() {
+        let base = TempDir::new();
+        let directories = [
+            ("16x16/apps", "Size=16\nType=Fixed"),
+            ("24x24/apps", "Size=24\nType=Fixed"),
+            ("32x32/apps", "Size=32\nType=Fixed"),
+            ("64x64/apps", "Size=64\nType=Fixed"),
+        ];
+        base.write("Theme/index.theme", &theme("Theme", "", &directories));
+        let exact = base.write("Theme/32x32/apps/editor.svg", "");
+        base.write("Theme/64x64/apps/editor.png", "");
+        let closest = base.write("Theme/24x24/apps/player.png", "");
+        base.write("Theme/64x64/apps/player.png", "");
+        // 同一目录中 png 优先于 svg
+        let png = base.write("Theme/16x16/apps/viewer.png", "");
+        base.write("Theme/16x16/apps/viewer.svg", "");
+
+        let theme = IconTheme::load("Theme", std::slice::from_ref(&base.0)).unwrap();
+        assert_eq!(theme.lookup("editor", 32), Some(exact));
+        assert_eq!(theme.lookup("player", 32), Some(closest));
+        assert_eq!(theme.lookup("viewer", 32), Some(png));
+        assert_eq!(theme.lookup("missing", 32), None);
+    }
+
+    #[test]
+    fn find_icon_follows_inherits_then_hicolor_then_loose_files() {
+        let base = TempDir::new();
+        let apps = "32x32/apps";
+        let keys = "Size=32\nType=Fixed";
+        // 循环继承不能导致无限递归
+        base.write("User/index.theme", &theme("User", "Parent", &[(apps, keys)]));
+        base.write("Parent/index.theme", &theme("Parent", "User", &[(apps, keys)]));
+        base.write("hicolor/index.theme", &theme("Hicolor", "", &[(apps, keys)]));
+
+        let own = base.write("User/32x32/apps/both.png", "");
+        base.write("hicolor/32x32/apps/both.png", "");
+        let inherited = base.write("Parent/32x32/apps/inherited.png", "");
+        let hicolor = base.write("hicolor/32x32/apps/fallback.png", "");
+        let loose = base.write("loose.svg", "");
+
+        let dirs = std::slice::from_ref(&base.0);
+        let find = |name: &str, theme: Option<&str>| find_icon(name, 32, theme, dirs);
+        assert_eq!(find("both", Some("User")), Some(own));
+        assert_eq!(find("inherited", Some("User")), Some(inherited));
+        assert_eq!(find("fallback", Some("User")), Some(hicolor.clone()));
+        assert_eq!(find("fallback", None), Some(hicolor.clone()));
+        assert_eq!(find("fallback", Some("Missing")), Some(hicolor));
+        assert_eq!(find("loose", Some("User")), Some(loose));
+        assert_eq!(find("missing", Some("User")), None);
+    }
+
+    #[test]
+    fn desktop_entry_matching_order() {
+        let home = TempDir::new();
+        let system = TempDir::new();
+        let entry = |wm_class: &str| format!("[Desktop Entry]\nName=App\nIcon=app\nStartupWMClass={}\n", wm_class);
+
+        // 文件名完全一致 → 忽略大小写 → StartupWMClass → 反向域名后缀，每一步都优先于后面的
+        let exact = home.write("applications/firefox.desktop", &entry(""));
+        system.write("applications/browser.desktop", &entry("firefox"));
+        let case_insensitive = home.write("applications/thunderbird.desktop", &entry(""));
+        system.write("applications/mail.desktop", &entry("Thunderbird"));
+        let wm_class = home.write("applications/vendor/vscode.desktop", &entry("Code"));
+        system.write("applications/org.example.Code.desktop", &entry(""));
+        let suffix = system.write("applications/org.gnome.Nautilus.desktop", &entry(""));
+        // 同一 StartupWMClass 以优先级更高的数据目录为准
+        system.write("applications/editor.desktop", &entry("code"));
+
+        let index = DesktopIndex::build(&[home.0.clone(), system.0.clone()]);
+        assert_eq!(index.find("firefox"), Some(exact));
+        assert_eq!(index.find("Thunderbird"), Some(case_insensitive));
+        assert_eq!(index.find("code"), Some(wm_class));
+        assert_eq!(index.find("nautilus"), Some(suffix));
+        assert_eq!(index.find("missing"), None);
+    }
+
+    #[test]
+    fn fit_transform_keeps_aspect_ratio_centered() {
+        let map = |transform: Transform, x: f32, y: f32| {
+            let mut point = [resvg::tiny_skia::Point::from_xy(x, y)];
+            transform.map_points(&mut point);
+            (point[0].x, point[0].y)
+        };
+
+        // 横向图标：宽度撑满，上下留白
+        let wide = fit_transform(64.0, 32.0);
+        assert_eq!(map(wide, 0.0, 0.0), (0.0, 8.0));
+        assert_eq!(map(wide, 64.0, 32.0), (32.0, 24.0));
+
+        // 纵向图标：高度撑满，左右留白
+        let tall = fit_transform(16.0, 64.0);
+        assert_eq!(map(tall, 0.0, 0.0), (12.0, 0.0));
+        assert_eq!(map(tall, 16.0, 64.0), (20.0, 32.0));
+
+        // 小图标放大
+        let small = fit_transform(16.0, 16.0);
+        assert_eq!(map(small, 16.0, 16.0), (32.0, 32.0));
+    }
+}
@@ -0,0 +1,320 @@
+//! Linux 媒体播放信息获取模块
+//! 基于 MPRIS D-Bus 接口 (org.mpris.MediaPlayer2.Player)
+
+use crate::platform::{MediaKind, MediaMetadata, PlaybackState};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use zbus::blocking::fdo::{DBusProxy, PropertiesProxy};
+use zbus::blocking::Connection;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{OwnedValue, Value};
+
+const MPRIS_BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const MPRIS_PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+/// MPRIS 规范中表示“无曲目”的 trackid
+const MPRIS_NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// 媒体信息缓存
+struct MediaCache {
+    metadata: Option<MediaMetadata>,
+    playback_state: Option<PlaybackState>,
+    last_update: Instant,
+    /// 上一次读取的封面 URL，未变化时复用封面数据
+    artwork_url: Option<String>,
+}
+
+impl Default for MediaCache {
+    fn default() -> Self {
+        Self {
+            metadata: None,
+            playback_state: None,
+            last_update: Instant::now() - Duration::from_secs(10), // 确保首次会更新
+            artwork_url: None,
+        }
+    }
+}
+
+// 全局缓存，缓存时间 200ms（避免频繁调用 D-Bus）
+static MEDIA_CACHE: Mutex<Option<MediaCache>> = Mutex::new(None);
+const CACHE_DURATION_MS: u64 = 200;
+
+// Session bus 连接（出错时重置）
+static SESSION_BUS: Mutex<Option<Connection>> = Mutex::new(None);
+
+/// 单个播放器的 Player 接口属性快照
+struct PlayerSnapshot {
+    bus_name: String,
+    status: String,
+    properties: HashMap<String, OwnedValue>,
+}
+
+fn session_bus() -> Result<Connection, String> {
+    let mut guard = SESSION_BUS.lock().map_err(|e| format!("D-Bus 连接锁定失败: {}", e))?;
+    if let Some(conn) = guard.as_ref() {
+        return Ok(conn.clone());
+    }
+    let conn = Connection::session().map_err(|e| format!("无法连接 D-Bus session bus: {}", e))?;
+    *guard = Some(conn.clone());
+    Ok(conn)
+}
+
+fn reset_session_bus() {
+    if let Ok(mut guard) = SESSION_BUS.lock() {
+        *guard = None;
+    }
+}
+
+/// 读取指定对象上某个接口的全部属性
+fn get_all_properties(conn: &Connection, bus_name: &str, interface: &'static str) -> Option<HashMap<String, OwnedValue>> {
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(bus_name.to_string())
+        .ok()?
+        .path(MPRIS_OBJECT_PATH)
+        .ok()?
+        .build()
+        .ok()?;
+    proxy.get_all(InterfaceName::from_static_str_unchecked(interface)).ok()
+}
+
+/// 选出当前活跃的播放器：优先正在播放的，其次是暂停的
+fn find_active_player(conn: &Connection) -> Result<Option<PlayerSnapshot>, String> {
+    let names = DBusProxy::new(conn)
+        .map_err(|e| format!("创建 D-Bus 代理失败: {}", e))?
+        .list_names()
+        .map_err(|e| format!("获取 D-Bus 名称列表失败: {}", e))?;
+
+    let mut paused = None;
+    for name in names {
+        let bus_name = name.to_string();
+        if !bus_name.starts_with(MPRIS_BUS_PREFIX) {
+            continue;
+        }
+
+        let Some(properties) = get_all_properties(conn, &bus_name, MPRIS_PLAYER_INTERFACE) else {
+            continue;
+        };
+        let status = properties
+            .get("PlaybackStatus")
+            .and_then(|v| value_to_string(v))
+            .unwrap_or_default();
+
+        match status.as_str() {
+            "Playing" => return Ok(Some(PlayerSnapshot { bus_name, status, properties })),
+            "Paused" if paused.is_none() => {
+                paused = Some(PlayerSnapshot { bus_name, status, properties });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(paused)
+}
+
+/// 获取播放器标识：优先使用 DesktopEntry，回退到 bus name 后缀
+fn player_identifier(conn: &Connection, bus_name: &str) -> String {
+    let desktop_entry = get_all_properties(conn, bus_name, MPRIS_ROOT_INTERFACE)
+        .and_then(|props| props.get("DesktopEntry").and_then(|v| value_to_string(v)))
+        .filter(|entry| !entry.is_empty());
+
+    desktop_entry.unwrap_or_else(|| {
+        let suffix = bus_name.trim_start_matches(MPRIS_BUS_PREFIX);
+        // 多实例播放器的名称形如 firefox.instance_1_23，去掉实例部分
+        suffix.split(".instance").next().unwrap_or(suffix).to_string()
+    })
+}
+
+fn value_to_string(value: &Value<'_>) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.as_str().to_string()),
+        Value::ObjectPath(path) => Some(path.as_str().to_string()),
+        Value::Value(inner) => value_to_string(inner),
+        _ => None,
+    }
+}
+
+fn value_to_string_list(value: &Value<'_>) -> Option<Vec<String>> {
+    match value {
+        Value::Array(array) => Some(
+            array
+                .iter()
+                .filter_map(value_to_string)
+                .collect(),
+        ),
+        Value::Str(s) => Some(vec![s.as_str().to_string()]),
+        Value::Value(inner) => value_to_string_list(inner),
+        _ => None,
+    }
+}
+
+fn value_to_i64(value: &Value<'_>) -> Option<i64> {
+    match value {
+        Value::I64(v) => Some(*v),
+        Value::U64(v) => i64::try_from(*v).ok(),
+        Value::I32(v) => Some(*v as i64),
+        Value::U32(v) => Some(*v as i64),
+        Value::F64(v) => Some(*v as i64),
+        Value::Value(inner) => value_to_i64(inner),
+        _ => None,
+    }
+}
+
+fn value_to_f64(value: &Value<'_>) -> Option<f64> {
+    match value {
+        Value::F64(v) => Some(*v),
+        _ => value_to_i64(value).map(|v| v as f64),
+    }
+}
+
+/// 读取 file:// 封面，其他协议（http 等）暂不下载
+fn load_artwork(art_url: &str) -> Option<(Arc<Vec<u8>>, String)> {
+    let url = url::Url::parse(art_url).ok()?;
+    if url.scheme() != "file" {
+        return None;
+    }
+    let path = url.to_file_path().ok()?;
+    let data = std::fs::read(&path).ok()?;
+    let mime_type = guess_image_mime(&data)?;
+    Some((Arc::new(data), mime_type.to_string()))
+}
+
+/// 根据文件头判断图片 MIME 类型
+fn guess_image_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// 从播放器属性提取数据并更新缓存
+fn update_cache_from_player(conn: &Connection, player: &PlayerSnapshot, cache: &mut MediaCache) {
+    let props = &player.properties;
+    let metadata: HashMap<String, OwnedValue> = props
+        .get("Metadata")
+        .and_then(|value| HashMap::<String, OwnedValue>::try_from(value.try_clone().ok()?).ok())
+        .unwrap_or_default();
+
+    let player_id = player_identifier(conn, &player.bus_name);
+    let title = metadata.get("xesam:title").and_then(|v| value_to_string(v)).filter(|t| !t.is_empty());
+    let artist = metadata
+        .get("xesam:artist")
+        .and_then(|v| value_to_string_list(v))
+        .map(|artists| artists.join(", "))
+        .filter(|a| !a.is_empty());
+    let album = metadata.get("xesam:album").and_then(|v| value_to_string(v)).filter(|a| !a.is_empty());
+    // mpris:length 与 Position 的单位均为微秒
+    let duration = metadata
+        .get("mpris:length")
+        .and_then(|v| value_to_i64(v))
+        .map(|us| us as f64 / 1_000_000.0)
+        .unwrap_or(0.0);
+    let track_id = metadata
+        .get("mpris:trackid")
+        .and_then(|v| value_to_string(v))
+        .filter(|id| !id.is_empty() && id != MPRIS_NO_TRACK);
+    let art_url = metadata.get("mpris:artUrl").and_then(|v| value_to_string(v));
+
+    // 封面未变化时复用缓存，避免每次重新读取文件
+    let (artwork_data, artwork_mime_type) = if cache.artwork_url == art_url
+        && cache.metadata.as_ref().and_then(|m| m.artwork_data.as_ref()).is_some()
+    {
+        let cached = cache.metadata.as_ref();
+        (
+            cached.and_then(|m| m.artwork_data.clone()),
+            cached.and_then(|m| m.artwork_mime_type.clone()),
+        )
+    } else {
+        cache.artwork_url = art_url.clone();
+        match art_url.as_deref().and_then(load_artwork) {
+            Some((data, mime)) => (Some(data), Some(mime)),
+            None => (None, None),
+        }
+    };
+
+    // 内容标识符：优先使用 trackid，否则使用 player + title + album 的组合
+    let content_item_identifier = track_id.map(|id| format!("{}:{}", player_id, id)).unwrap_or_else(|| {
+        format!(
+            "{}:{}:{}",
+            player_id,
+            title.as_deref().unwrap_or(""),
+            album.as_deref().unwrap_or("")
+        )
+    });
+
+    let playing = player.status == "Playing";
+    let playback_rate = if playing {
+        props.get("Rate").and_then(|v| value_to_f64(v)).unwrap_or(1.0)
+    } else {
+        0.0
+    };
+    let elapsed_time = props
+        .get("Position")
+        .and_then(|v| value_to_i64(v))
+        .map(|us| us as f64 / 1_000_000.0)
+        .unwrap_or(0.0);
+
+    cache.metadata = Some(MediaMetadata {
+        kind: MediaKind::Unknown,
+        source_app_id: Some(player_id),
+        title,
+        artist,
+        album,
+        duration,
+        artwork_data,
+        artwork_mime_type,
+        content_item_identifier: Some(content_item_identifier),
+    });
+
+    cache.playback_state = Some(PlaybackState::new(playing, playback_rate, elapsed_time));
+
+    cache.last_update = Instant::now();
+}
+
+/// 刷新缓存（缓存有效时直接返回）
+fn with_fresh_cache<T>(f: impl Fn(&MediaCache) -> Option<T>) -> Result<Option<T>, String> {
+    let mut cache_guard = MEDIA_CACHE.lock().map_err(|e| format!("缓存锁定失败: {}", e))?;
+    let cache = cache_guard.get_or_insert_with(MediaCache::default);
+
+    if cache.last_update.elapsed() < Duration::from_millis(CACHE_DURATION_MS) {
+        return Ok(f(cache));
+    }
+
+    let conn = session_bus()?;
+    match find_active_player(&conn) {
+        Ok(Some(player)) => update_cache_from_player(&conn, &player, cache),
+        Ok(None) => {
+            // 清空缓存
+            cache.metadata = None;
+            cache.playback_state = None;
+            cache.artwork_url = None;
+            cache.last_update = Instant::now();
+        }
+        Err(e) => {
+            reset_session_bus();
+            return Err(e);
+        }
+    }
+
+    Ok(f(cache))
+}
+
+/// 获取当前播放状态
+pub fn get_playback_state() -> Result<Option<PlaybackState>, String> {
+    with_fresh_cache(|cache| cache.playback_state.clone())
+}
+
+/// 获取当前媒体元数据
+pub fn get_media_metadata() -> Result<Option<MediaMetadata>, String> {
+    with_fresh_cache(|cache| cache.metadata.clone())
+}
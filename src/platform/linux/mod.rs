@@ -0,0 +1,184 @@
+//! Linux 平台实现
+//!
+//! 窗口信息支持两种后端，运行时根据环境变量选择：
+//! - Wayland (`WAYLAND_DISPLAY`)：wlr-foreign-toplevel-management 协议
+//! - X11 (`DISPLAY`)：EWMH 属性，在 Wayland 下也可作为 XWayland 的回退
+
+use super::{
+    ChannelWatcher, MediaMetadata, PlaybackState, PlatformProvider, PollingWatcher, WindowInfo, WindowWatcher,
+};
+use std::sync::OnceLock;
+
+mod icon;
+pub mod media;
+mod wayland;
+mod x11;
+
+pub use icon::get_cached_app_icon;
+pub use media::{get_media_metadata, get_playback_state};
+
+/// 窗口信息后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowBackend {
+    /// Wayland wlr-foreign-toplevel-management
+    Wayland,
+    /// X11 / XWayland EWMH
+    X11,
+}
+
+static WINDOW_BACKEND: OnceLock<Option<WindowBackend>> = OnceLock::new();
+
+fn env_is_set(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+/// 检测可用的窗口信息后端
+///
+/// 优先使用 Wayland；合成器不支持 wlr 协议时（如 GNOME、KDE），
+/// 若存在 `DISPLAY` 则回退到 X11（只能看到 XWayland 窗口）
+fn detect_window_backend() -> Option<WindowBackend> {
+    if env_is_set("WAYLAND_DISPLAY") {
+        if wayland::is_available() {
+            tracing::info!("使用 Wayland (wlr-foreign-toplevel) 窗口后端");
+            return Some(WindowBackend::Wayland);
+        }
+        tracing::warn!("Wayland 合成器不支持 wlr-foreign-toplevel-management，尝试 X11");
+    }
+    if env_is_set("DISPLAY") {
+        tracing::info!("使用 X11 (EWMH) 窗口后端");
+        return Some(WindowBackend::X11);
+    }
+    None
+}
+
+/// 获取当前使用的窗口信息后端（首次调用时检测）
+pub fn window_backend() -> Option<WindowBackend> {
+    *WINDOW_BACKEND.get_or_init(detect_window_backend)
+}
+
+const NO_SESSION: &str = "未检测到图形会话 (WAYLAND_DISPLAY / DISPLAY 均未设置)";
+
+fn frontmost_window_on(backend: Option<WindowBackend>) -> Result<WindowInfo, String> {
+    match backend {
+        Some(WindowBackend::Wayland) => wayland::get_frontmost_window(),
+        Some(WindowBackend::X11) => x11::get_frontmost_window(),
+        None => Err(NO_SESSION.to_string()),
+    }
+}
+
+fn all_windows_on(backend: Option<WindowBackend>) -> Result<Vec<WindowInfo>, String> {
+    match backend {
+        Some(WindowBackend::Wayland) => wayland::get_all_windows(),
+        Some(WindowBackend::X11) => x11::get_all_windows(),
+        None => Err(NO_SESSION.to_string()),
+    }
+}
+
+/// 创建窗口变化监听器：X11 / Wayland 均使用事件推送，失败时回退到轮询
+fn window_watcher_on(backend: Option<WindowBackend>) -> Box<dyn WindowWatcher> {
+    let events = match backend {
+        Some(WindowBackend::Wayland) => wayland::watch_window_events(),
+        Some(WindowBackend::X11) => x11::watch_window_events(),
+        None => Err(NO_SESSION.to_string()),
+    };
+    let query = move || frontmost_window_on(backend);
+
+    match events {
+        Ok(events) => Box::new(ChannelWatcher::new(events, query)),
+        Err(e) => {
+            tracing::warn!("窗口事件监听不可用，回退到轮询: {}", e);
+            Box::new(PollingWatcher::new(query))
+        }
+    }
+}
+
+/// 获取当前前台窗口信息
+pub fn get_frontmost_window() -> Result<WindowInfo, String> {
+    frontmost_window_on(window_backend())
+}
+
+/// 获取所有窗口列表
+pub fn get_all_windows() -> Result<Vec<WindowInfo>, String> {
+    all_windows_on(window_backend())
+}
+
+/// 创建窗口变化监听器
+pub fn create_window_watcher() -> Box<dyn WindowWatcher> {
+    window_watcher_on(window_backend())
+}
+
+/// 请求必要的权限 (X11 / Wayland 下读取窗口信息不需要额外权限)
+pub fn request_permissions() -> Result<bool, String> {
+    Ok(true)
+}
+
+/// 检查权限状态
+pub fn check_permissions() -> bool {
+    true
+}
+
+/// Linux 平台实现
+///
+/// 默认使用运行时检测到的后端，也可以通过 [`LinuxProvider::with_backend`] 指定
+pub struct LinuxProvider {
+    backend: Option<WindowBackend>,
+}
+
+impl LinuxProvider {
+    pub fn new() -> Self {
+        Self { backend: window_backend() }
+    }
+
+    /// 使用指定的窗口信息后端
+    pub fn with_backend(backend: WindowBackend) -> Self {
+        Self { backend: Some(backend) }
+    }
+}
+
+impl Default for LinuxProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlatformProvider for LinuxProvider {
+    fn name(&self) -> &str {
+        match self.backend {
+            Some(WindowBackend::Wayland) => "linux-wayland",
+            Some(WindowBackend::X11) => "linux-x11",
+            None => "linux",
+        }
+    }
+
+    fn request_permissions(&self) -> Result<bool, String> {
+        request_permissions()
+    }
+
+    fn check_permissions(&self) -> bool {
+        check_permissions()
+    }
+
+    fn get_frontmost_window(&self) -> Result<WindowInfo, String> {
+        frontmost_window_on(self.backend)
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        all_windows_on(self.backend)
+    }
+
+    fn create_window_watcher(&self) -> Box<dyn WindowWatcher> {
+        window_watcher_on(self.backend)
+    }
+
+    fn get_playback_state(&self) -> Result<Option<PlaybackState>, String> {
+        get_playback_state()
+    }
+
+    fn get_media_metadata(&self) -> Result<Option<MediaMetadata>, String> {
+        get_media_metadata()
+    }
+
+    fn get_app_icon(&self, app_id: &str) -> Option<Vec<u8>> {
+        get_cached_app_icon(app_id)
+    }
+}
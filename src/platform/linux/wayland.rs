@@ -0,0 +1,291 @@
+//! Linux Wayland 窗口信息获取模块
+//! 基于 wlr-foreign-toplevel-management 协议 (zwlr_foreign_toplevel_manager_v1)
+//!
+//! 支持该协议的合成器：sway、Hyprland、river、labwc、Wayfire 等
+
+use super::icon::get_cached_app_icon;
+use crate::platform::{WindowEvent, WindowInfo};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry;
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+/// 单个 toplevel 的状态
+#[derive(Debug, Clone, Default)]
+struct Toplevel {
+    title: String,
+    app_id: String,
+    activated: bool,
+    /// 收到过 done 事件（此前的属性尚未提交）
+    ready: bool,
+}
+
+/// 已提交的 toplevel 快照（按创建顺序）
+#[derive(Debug, Clone, Default)]
+struct ToplevelSnapshot {
+    toplevels: Vec<Toplevel>,
+}
+
+/// 事件分发线程持有的状态
+#[derive(Default)]
+struct State {
+    toplevels: HashMap<ObjectId, Toplevel>,
+    order: Vec<ObjectId>,
+}
+
+impl State {
+    /// 当前激活窗口的 (id, title)
+    fn focus(&self) -> Option<(ObjectId, String)> {
+        self.toplevels
+            .iter()
+            .find(|(_, toplevel)| toplevel.ready && toplevel.activated)
+            .map(|(id, toplevel)| (id.clone(), toplevel.title.clone()))
+    }
+
+    fn snapshot(&self) -> ToplevelSnapshot {
+        ToplevelSnapshot {
+            toplevels: self
+                .order
+                .iter()
+                .filter_map(|id| self.toplevels.get(id))
+                .filter(|toplevel| toplevel.ready)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// 分发线程发布的最新快照
+static SNAPSHOT: Mutex<Option<ToplevelSnapshot>> = Mutex::new(None);
+
+/// 合成器连接状态
+enum Link {
+    Connected,
+    /// 连接失败或已断开，`RECONNECT_INTERVAL` 之后再试
+    Failed { error: String, at: Instant },
+}
+
+static LINK: Mutex<Option<Link>> = Mutex::new(None);
+
+/// 两次连接尝试的最小间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 窗口变化事件的订阅者
+static WATCHERS: Mutex<Vec<Sender<WindowEvent>>> = Mutex::new(Vec::new());
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            let id = toplevel.id();
+            state.toplevels.insert(id.clone(), Toplevel::default());
+            state.order.push(id);
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::Event;
+
+        let id = proxy.id();
+        if let Event::Closed = event {
+            state.toplevels.remove(&id);
+            state.order.retain(|other| *other != id);
+            proxy.destroy();
+            return;
+        }
+
+        let Some(toplevel) = state.toplevels.get_mut(&id) else {
+            return;
+        };
+        match event {
+            Event::Title { title } => toplevel.title = title,
+            Event::AppId { app_id } => toplevel.app_id = app_id,
+            Event::State { state } => {
+                // state 是以本机字节序存放的 u32 数组
+                let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                toplevel.activated = state
+                    .chunks_exact(4)
+                    .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .any(|value| value == activated);
+            }
+            Event::Done => toplevel.ready = true,
+            _ => {}
+        }
+    }
+}
+
+fn publish(snapshot: ToplevelSnapshot) {
+    if let Ok(mut guard) = SNAPSHOT.lock() {
+        *guard = Some(snapshot);
+    }
+}
+
+/// 通知所有订阅者，并移除已释放的订阅者
+fn notify(event: WindowEvent) {
+    if let Ok(mut watchers) = WATCHERS.lock() {
+        watchers.retain(|tx| tx.send(event).is_ok());
+    }
+}
+
+/// 连接合成器并启动事件分发线程
+fn connect() -> Result<(), String> {
+    let conn = Connection::connect_to_env()
+        .map_err(|e| format!("无法连接 Wayland 合成器: {}", e))?;
+    let (globals, mut queue) = registry_queue_init::<State>(&conn)
+        .map_err(|e| format!("Wayland registry 初始化失败: {}", e))?;
+    let qh = queue.handle();
+
+    let manager: ZwlrForeignToplevelManagerV1 = globals
+        .bind(&qh, 1..=3, ())
+        .map_err(|_| "合成器不支持 zwlr_foreign_toplevel_manager_v1".to_string())?;
+
+    // 首次同步，拿到已存在的窗口
+    let mut state = State::default();
+    queue
+        .roundtrip(&mut state)
+        .map_err(|e| format!("Wayland 事件分发失败: {}", e))?;
+    publish(state.snapshot());
+
+    std::thread::Builder::new()
+        .name("wayland-toplevel".to_string())
+        .spawn(move || {
+            // 保持 manager 存活
+            let _manager = manager;
+            loop {
+                let previous_focus = state.focus();
+                if let Err(e) = queue.blocking_dispatch(&mut state) {
+                    tracing::warn!("Wayland 连接已断开: {}", e);
+                    // 之后的调用在间隔过后重新连接
+                    if let Ok(mut link) = LINK.lock() {
+                        *link = Some(Link::Failed { error: "Wayland 合成器连接已断开".to_string(), at: Instant::now() });
+                    }
+                    if let Ok(mut snapshot) = SNAPSHOT.lock() {
+                        *snapshot = None;
+                    }
+                    // 断开所有订阅者，让其回退到轮询
+                    if let Ok(mut watchers) = WATCHERS.lock() {
+                        watchers.clear();
+                    }
+                    break;
+                }
+                publish(state.snapshot());
+
+                match (previous_focus, state.focus()) {
+                    (Some((old_id, _)), Some((id, _))) if old_id != id => notify(WindowEvent::FocusChanged),
+                    (Some((_, old_title)), Some((_, title))) if old_title != title => notify(WindowEvent::TitleChanged),
+                    (None, Some(_)) | (Some(_), None) => notify(WindowEvent::FocusChanged),
+                    _ => {}
+                }
+            }
+        })
+        .map_err(|e| format!("无法启动 Wayland 事件线程: {}", e))?;
+
+    Ok(())
+}
+
+/// 确保已连接合成器（首次调用或断开后重新连接）
+fn ensure_connected() -> Result<(), String> {
+    let mut link = LINK.lock().map_err(|e| format!("Wayland 状态锁定失败: {}", e))?;
+    match &*link {
+        Some(Link::Connected) => return Ok(()),
+        Some(Link::Failed { error, at }) if at.elapsed() < RECONNECT_INTERVAL => return Err(error.clone()),
+        _ => {}
+    }
+    let result = connect();
+    *link = Some(match &result {
+        Ok(()) => Link::Connected,
+        Err(error) => Link::Failed { error: error.clone(), at: Instant::now() },
+    });
+    result
+}
+
+/// 当前合成器是否支持 wlr-foreign-toplevel-management
+pub fn is_available() -> bool {
+    ensure_connected().is_ok()
+}
+
+fn current_snapshot() -> Result<ToplevelSnapshot, String> {
+    ensure_connected()?;
+    Ok(SNAPSHOT
+        .lock()
+        .map_err(|e| format!("Wayland 状态锁定失败: {}", e))?
+        .clone()
+        .unwrap_or_default())
+}
+
+fn to_window_info(toplevel: Toplevel) -> WindowInfo {
+    let app_id = if toplevel.app_id.is_empty() { None } else { Some(toplevel.app_id) };
+    WindowInfo {
+        title: toplevel.title,
+        icon_data: app_id.as_deref().and_then(get_cached_app_icon),
+        // 协议不提供进程信息，使用 app_id 作为进程名
+        process_name: app_id.clone().unwrap_or_else(|| "Unknown".to_string()),
+        pid: 0,
+        app_id,
+    }
+}
+
+/// 监听窗口变化事件（事件来自分发线程）
+pub fn watch_window_events() -> Result<Receiver<WindowEvent>, String> {
+    ensure_connected()?;
+    let (tx, rx) = mpsc::channel();
+    WATCHERS
+        .lock()
+        .map_err(|e| format!("Wayland 状态锁定失败: {}", e))?
+        .push(tx);
+    Ok(rx)
+}
+
+/// 获取当前前台窗口信息
+pub fn get_frontmost_window() -> Result<WindowInfo, String> {
+    current_snapshot()?
+        .toplevels
+        .into_iter()
+        .find(|toplevel| toplevel.activated)
+        .map(to_window_info)
+        .ok_or_else(|| "没有处于激活状态的窗口".to_string())
+}
+
+/// 获取所有窗口列表
+pub fn get_all_windows() -> Result<Vec<WindowInfo>, String> {
+    Ok(current_snapshot()?.toplevels.into_iter().map(to_window_info).collect())
+}
@@ -0,0 +1,528 @@
+//! Linux X11 窗口信息获取模块
+//! 基于 EWMH 属性 (_NET_ACTIVE_WINDOW / _NET_WM_NAME / _NET_WM_PID / _NET_WM_ICON)
+
+use super::icon::get_cached_app_icon;
+use crate::platform::{WindowEvent, WindowInfo};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+x11rb::atom_manager! {
+    /// 本模块需要用到的 X11 原子
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_ICON,
+        UTF8_STRING,
+    }
+}
+
+/// 图标目标尺寸（与 macOS 保持一致，32x32）
+const ICON_SIZE: u32 = 32;
+
+/// X11 连接状态（连接出错时重置，下次调用重新连接）
+struct X11State {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+static X11_STATE: Mutex<Option<X11State>> = Mutex::new(None);
+
+impl X11State {
+    fn connect() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None)
+            .map_err(|e| format!("无法连接 X11 显示服务器: {}", e))?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)
+            .map_err(|e| format!("X11 原子请求失败: {}", e))?
+            .reply()
+            .map_err(|e| format!("X11 原子请求失败: {}", e))?;
+
+        Ok(Self { conn, root, atoms })
+    }
+
+    /// 读取 32 位属性值
+    fn get_u32_property(&self, window: Window, property: u32, type_: AtomEnum) -> Option<Vec<u32>> {
+        let reply = self.conn
+            .get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?;
+        let values: Vec<u32> = reply.value32()?.collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(values)
+        }
+    }
+
+    /// 读取原始字节属性值及其实际类型
+    fn get_typed_bytes_property(&self, window: Window, property: u32, type_: u32) -> Option<(u32, Vec<u8>)> {
+        let reply = self.conn
+            .get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()?;
+        if reply.format != 8 || reply.value.is_empty() {
+            return None;
+        }
+        Some((reply.type_, reply.value))
+    }
+
+    /// 读取原始字节属性值
+    fn get_bytes_property(&self, window: Window, property: u32, type_: u32) -> Option<Vec<u8>> {
+        self.get_typed_bytes_property(window, property, type_).map(|(_, value)| value)
+    }
+
+    /// 获取当前激活的窗口
+    fn active_window(&self) -> Result<Window, String> {
+        self.get_u32_property(self.root, self.atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW)
+            .and_then(|values| values.first().copied())
+            .filter(|&window| window != x11rb::NONE)
+            .ok_or_else(|| "窗口管理器未提供 _NET_ACTIVE_WINDOW".to_string())
+    }
+
+    /// 获取窗口标题：优先 _NET_WM_NAME (UTF-8)，回退到 WM_NAME
+    fn window_title(&self, window: Window) -> String {
+        if let Some(bytes) = self.get_bytes_property(window, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING) {
+            return String::from_utf8_lossy(&bytes).into_owned();
+        }
+        // WM_NAME 一般是 Latin-1 (STRING)，部分程序也会写入 UTF8_STRING，按实际类型解码
+        self.get_typed_bytes_property(window, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into())
+            .map(|(type_, bytes)| decode_text(&bytes, type_, self.atoms.UTF8_STRING))
+            .unwrap_or_default()
+    }
+
+    /// 获取窗口所属进程 ID
+    fn window_pid(&self, window: Window) -> Option<i32> {
+        self.get_u32_property(window, self.atoms._NET_WM_PID, AtomEnum::CARDINAL)
+            .and_then(|values| values.first().copied())
+            .map(|pid| pid as i32)
+    }
+
+    /// 获取 WM_CLASS 的 (instance, class)
+    fn window_class(&self, window: Window) -> Option<(String, String)> {
+        let bytes = self.get_bytes_property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+        let mut parts = bytes
+            .split(|&b| b == 0)
+            .map(|part| String::from_utf8_lossy(part).into_owned());
+        let instance = parts.next().unwrap_or_default();
+        let class = parts.next().unwrap_or_default();
+        Some((instance, class))
+    }
+
+    /// 获取窗口图标并转换为 PNG
+    fn window_icon(&self, window: Window) -> Option<Vec<u8>> {
+        let data = self.get_u32_property(window, self.atoms._NET_WM_ICON, AtomEnum::CARDINAL)?;
+        icon_to_png(&data)
+    }
+
+    fn window_info(&self, window: Window) -> WindowInfo {
+        let title = self.window_title(window);
+        let pid = self.window_pid(window).unwrap_or(0);
+        let class = self.window_class(window);
+
+        let process_name = read_process_name(pid)
+            .or_else(|| class.as_ref().map(|(_, class)| class.clone()).filter(|c| !c.is_empty()))
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        // 图标：优先使用图标主题中的应用图标（按 WM_CLASS 缓存），回退到 _NET_WM_ICON
+        let icon_data = class
+            .as_ref()
+            .and_then(|(instance, class)| get_cached_app_icon(class).or_else(|| get_cached_app_icon(instance)))
+            .or_else(|| self.window_icon(window));
+
+        // 使用 WM_CLASS 的 class 部分作为 app_id，回退到可执行路径
+        let app_id = class
+            .map(|(_, class)| class)
+            .filter(|class| !class.is_empty())
+            .or_else(|| read_process_exe(pid));
+
+        WindowInfo {
+            title,
+            icon_data,
+            process_name,
+            pid,
+            app_id,
+        }
+    }
+
+    /// 设置本连接在指定窗口上关注的事件
+    fn select_events(&self, window: Window, mask: EventMask) {
+        // 窗口可能已被销毁，错误会以事件形式返回，忽略即可
+        let _ = self.conn.change_window_attributes(window, &ChangeWindowAttributesAux::new().event_mask(mask));
+        let _ = self.conn.flush();
+    }
+}
+
+/// 在 X11 连接上执行操作，连接失败或出错时重置连接
+fn with_state<T>(f: impl FnOnce(&X11State) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = X11_STATE.lock().map_err(|e| format!("X11 状态锁定失败: {}", e))?;
+
+    if guard.is_none() {
+        *guard = Some(X11State::connect()?);
+    }
+
+    let state = guard.as_ref().ok_or("X11 连接不可用")?;
+    let result = f(state);
+
+    // 连接已断开（例如 X 服务器重启），下次调用时重新连接
+    if result.is_err() && state.conn.flush().is_err() {
+        *guard = None;
+    }
+
+    result
+}
+
+/// 按属性类型解码文本：UTF8_STRING 为 UTF-8，STRING 为 Latin-1
+///
+/// 其他类型（如 COMPOUND_TEXT）是合法 UTF-8 时按 UTF-8 解析，否则按 Latin-1 处理
+fn decode_text(bytes: &[u8], type_: u32, utf8_string: u32) -> String {
+    let latin1 = || bytes.iter().map(|&b| b as char).collect();
+    if type_ == utf8_string {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else if type_ == u32::from(AtomEnum::STRING) {
+        latin1()
+    } else {
+        std::str::from_utf8(bytes).map(str::to_string).unwrap_or_else(|_| latin1())
+    }
+}
+
+/// 从 /proc 读取进程名
+fn read_process_name(pid: i32) -> Option<String> {
+    if pid <= 0 {
+        return None;
+    }
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .map(|name| name.trim_end().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// 从 /proc 读取可执行文件路径
+fn read_process_exe(pid: i32) -> Option<String> {
+    if pid <= 0 {
+        return None;
+    }
+    std::fs::read_link(format!("/proc/{}/exe", pid))
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// 将 _NET_WM_ICON 数据转换为 32x32 PNG
+///
+/// 数据格式为若干组 `width, height, width * height 个 ARGB 像素`
+fn icon_to_png(data: &[u32]) -> Option<Vec<u8>> {
+    // 选出最合适的尺寸：优先不小于目标尺寸中最小的，否则取最大的
+    let mut best: Option<(u32, u32, &[u32])> = None;
+    let mut offset = 0;
+    while offset + 2 <= data.len() {
+        let (width, height) = (data[offset], data[offset + 1]);
+        let len = (width as usize).checked_mul(height as usize)?;
+        let start = offset + 2;
+        if width == 0 || height == 0 || start + len > data.len() {
+            break;
+        }
+        let pixels = &data[start..start + len];
+        best = match best {
+            None => Some((width, height, pixels)),
+            Some((best_width, _, _)) => {
+                let better = if best_width >= ICON_SIZE {
+                    width >= ICON_SIZE && width < best_width
+                } else {
+                    width > best_width
+                };
+                if better { Some((width, height, pixels)) } else { best }
+            }
+        };
+        offset = start + len;
+    }
+
+    let (width, height, pixels) = best?;
+
+    // ARGB (非预乘) -> RGBA
+    let rgba: Vec<u8> = pixels
+        .iter()
+        .flat_map(|&argb| {
+            let [a, r, g, b] = argb.to_be_bytes();
+            [r, g, b, a]
+        })
+        .collect();
+
+    let (width, height, rgba) = if width > ICON_SIZE || height > ICON_SIZE {
+        (ICON_SIZE, ICON_SIZE, fit_rgba(&rgba, width, height))
+    } else {
+        (width, height, rgba)
+    };
+
+    encode_png(&rgba, width, height)
+}
+
+/// 等比缩小并居中到 ICON_SIZE x ICON_SIZE 的透明画布（与 icon.rs 的 fit_transform 一致）
+fn fit_rgba(src: &[u8], src_width: u32, src_height: u32) -> Vec<u8> {
+    let longest = src_width.max(src_height);
+    let fit = |side: u32| ((side * ICON_SIZE + longest / 2) / longest).clamp(1, ICON_SIZE);
+    let (width, height) = (fit(src_width), fit(src_height));
+    let scaled = downscale_rgba(src, src_width, src_height, width, height);
+
+    let mut canvas = vec![0u8; (ICON_SIZE * ICON_SIZE * 4) as usize];
+    let (left, top) = ((ICON_SIZE - width) / 2, (ICON_SIZE - height) / 2);
+    for (y, row) in scaled.chunks_exact((width * 4) as usize).enumerate() {
+        let start = (((top + y as u32) * ICON_SIZE + left) * 4) as usize;
+        canvas[start..start + row.len()].copy_from_slice(row);
+    }
+    canvas
+}
+
+/// 区域平均缩小 RGBA 图像
+fn downscale_rgba(src: &[u8], src_width: u32, src_height: u32, dst_width: u32, dst_height: u32) -> Vec<u8> {
+    let mut dst = Vec::with_capacity((dst_width * dst_height * 4) as usize);
+    for dy in 0..dst_height {
+        let y0 = dy * src_height / dst_height;
+        let y1 = ((dy + 1) * src_height / dst_height).max(y0 + 1);
+        for dx in 0..dst_width {
+            let x0 = dx * src_width / dst_width;
+            let x1 = ((dx + 1) * src_width / dst_width).max(x0 + 1);
+
+            let mut sum = [0u64; 4];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let i = ((y * src_width + x) * 4) as usize;
+                    let alpha = src[i + 3] as u64;
+                    // 按 alpha 加权，避免透明像素的颜色渗到边缘
+                    sum[0] += src[i] as u64 * alpha;
+                    sum[1] += src[i + 1] as u64 * alpha;
+                    sum[2] += src[i + 2] as u64 * alpha;
+                    sum[3] += alpha;
+                }
+            }
+
+            let count = ((y1 - y0) * (x1 - x0)) as u64;
+            match std::num::NonZeroU64::new(sum[3]) {
+                Some(alpha) => {
+                    dst.push((sum[0] / alpha) as u8);
+                    dst.push((sum[1] / alpha) as u8);
+                    dst.push((sum[2] / alpha) as u8);
+                    dst.push((sum[3] / count) as u8);
+                }
+                None => dst.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+    dst
+}
+
+/// 将 RGBA 数据编码为 PNG
+pub(crate) fn encode_png(rgba: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().ok()?;
+        writer.write_image_data(rgba).ok()?;
+    }
+    Some(bytes)
+}
+
+/// 获取当前前台窗口信息
+pub fn get_frontmost_window() -> Result<WindowInfo, String> {
+    with_state(|state| {
+        let window = state.active_window()?;
+        Ok(state.window_info(window))
+    })
+}
+
+/// 获取所有窗口列表 (基于 _NET_CLIENT_LIST)
+pub fn get_all_windows() -> Result<Vec<WindowInfo>, String> {
+    with_state(|state| {
+        let windows = state
+            .get_u32_property(state.root, state.atoms._NET_CLIENT_LIST, AtomEnum::WINDOW)
+            .ok_or("窗口管理器未提供 _NET_CLIENT_LIST")?;
+        Ok(windows.into_iter().map(|window| state.window_info(window)).collect())
+    })
+}
+
+/// 监听窗口变化事件
+///
+/// 使用独立的 X11 连接：在根窗口上监听 `_NET_ACTIVE_WINDOW` 变化，
+/// 在当前激活窗口上监听 `_NET_WM_NAME` / `WM_NAME` 变化
+pub fn watch_window_events() -> Result<Receiver<WindowEvent>, String> {
+    let state = X11State::connect()?;
+    state.conn
+        .change_window_attributes(state.root, &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE))
+        .map_err(|e| format!("X11 事件订阅失败: {}", e))?
+        .check()
+        .map_err(|e| format!("X11 事件订阅失败: {}", e))?;
+
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::Builder::new()
+        .name("x11-window-watcher".to_string())
+        .spawn(move || {
+            let mut active = state.active_window().ok();
+            if let Some(window) = active {
+                state.select_events(window, EventMask::PROPERTY_CHANGE);
+            }
+
+            loop {
+                let event = match state.conn.wait_for_event() {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::warn!("X11 事件连接已断开: {}", e);
+                        break;
+                    }
+                };
+                let Event::PropertyNotify(notify) = event else {
+                    continue;
+                };
+
+                let change = if notify.window == state.root && notify.atom == state.atoms._NET_ACTIVE_WINDOW {
+                    let current = state.active_window().ok();
+                    if current == active {
+                        None
+                    } else {
+                        if let Some(window) = active {
+                            state.select_events(window, EventMask::NO_EVENT);
+                        }
+                        if let Some(window) = current {
+                            state.select_events(window, EventMask::PROPERTY_CHANGE);
+                        }
+                        active = current;
+                        Some(WindowEvent::FocusChanged)
+                    }
+                } else if Some(notify.window) == active
+                    && (notify.atom == state.atoms._NET_WM_NAME || notify.atom == u32::from(AtomEnum::WM_NAME))
+                {
+                    Some(WindowEvent::TitleChanged)
+                } else {
+                    None
+                };
+
+                // 接收端已释放，结束线程
+                if let Some(change) = change {
+                    if tx.send(change).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+        .map_err(|e| format!("无法启动 X11 事件线程: {}", e))?;
+
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    /// 测试用的原子编号，真实值由服务器分配
+    const UTF8_STRING: u32 = 1001;
+    /// 既不是 STRING 也不是 UTF8_STRING 的类型
+    const COMPOUND_TEXT: u32 = 1000;
+
+    /// 单色 ARGB 图标的 _NET_WM_ICON 数据
+    fn solid_icon(width: u32, height: u32, argb: u32) -> Vec<u32> {
+        let mut data = vec![width, height];
+        data.extend(std::iter::repeat_n(argb, (width * height) as usize));
+        data
+    }
+
+    /// 解码 PNG 为 (宽, 高, RGBA)
+    fn decode_png(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(std::io::Cursor::new(bytes)).read_info().unwrap();
+        let mut rgba = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut rgba).unwrap();
+        (info.width, info.height, rgba)
+    }
+
+    #[test]
+    fn wide_icon_keeps_aspect_ratio() {
+        let (width, height, rgba) = decode_png(&icon_to_png(&solid_icon(64, 32, 0xffff0000)).unwrap());
+        assert_eq!((width, height), (32, 32));
+        let pixel = |x: u32, y: u32| &rgba[((y * 32 + x) * 4) as usize..][..4];
+        // 缩放为 32x16，上下各留 8 行透明
+        for x in 0..32 {
+            assert_eq!(pixel(x, 7), [0, 0, 0, 0]);
+            assert_eq!(pixel(x, 8), [255, 0, 0, 255]);
+            assert_eq!(pixel(x, 23), [255, 0, 0, 255]);
+            assert_eq!(pixel(x, 24), [0, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn tall_icon_is_centered() {
+        let canvas = fit_rgba(&[0, 0, 255, 255].repeat(40 * 100), 40, 100);
+        assert_eq!(canvas.len(), 32 * 32 * 4);
+        // 缩放为 13x32，水平居中于第 9..22 列
+        let column_alpha = |x: usize, alpha: u8| (0..32).all(|y| canvas[(y * 32 + x) * 4 + 3] == alpha);
+        assert!(column_alpha(8, 0));
+        assert!(column_alpha(9, 255));
+        assert!(column_alpha(21, 255));
+        assert!(column_alpha(22, 0));
+    }
+
+    #[test]
+    fn small_icon_is_left_alone() {
+        let (width, height, _) = decode_png(&icon_to_png(&solid_icon(24, 16, 0xff00ff00)).unwrap());
+        assert_eq!((width, height), (24, 16));
+    }
+
+    #[test]
+    fn decode_text_by_type() {
+        let string = u32::from(AtomEnum::STRING);
+        assert_eq!(decode_text(b"Caf\xe9", string, UTF8_STRING), "Café");
+        // 声明为 STRING 的字节不按 UTF-8 解析
+        assert_eq!(decode_text("Café".as_bytes(), string, UTF8_STRING), "CafÃ©");
+        assert_eq!(decode_text("Café ☕".as_bytes(), UTF8_STRING, UTF8_STRING), "Café ☕");
+        assert_eq!(decode_text("Café".as_bytes(), COMPOUND_TEXT, UTF8_STRING), "Café");
+        assert_eq!(decode_text(b"Caf\xe9", COMPOUND_TEXT, UTF8_STRING), "Café");
+    }
+
+    #[test]
+    #[ignore = "需要 X 服务器，例如 xvfb-run cargo test -- --ignored"]
+    fn window_title_from_the_server() {
+        let state = X11State::connect().unwrap();
+        let conn = &state.conn;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            state.root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .unwrap()
+        .check()
+        .unwrap();
+        let set_title = |property: u32, type_: u32, title: &[u8]| {
+            conn.change_property8(PropMode::REPLACE, window, property, type_, title).unwrap().check().unwrap();
+        };
+
+        set_title(AtomEnum::WM_NAME.into(), AtomEnum::STRING.into(), b"Caf\xe9");
+        assert_eq!(state.window_title(window), "Café");
+
+        set_title(AtomEnum::WM_NAME.into(), state.atoms.UTF8_STRING, "Café ☕".as_bytes());
+        assert_eq!(state.window_title(window), "Café ☕");
+
+        // _NET_WM_NAME 优先于 WM_NAME
+        set_title(state.atoms._NET_WM_NAME, state.atoms.UTF8_STRING, "タイトル".as_bytes());
+        assert_eq!(state.window_title(window), "タイトル");
+
+        conn.destroy_window(window).unwrap().check().unwrap();
+    }
+}
@@ -1,45 +1,12 @@
 //! macOS 媒体播放信息获取模块
 //! 使用 mediaremote-rs 库访问 MediaRemote.framework
 
+use super::super::{MediaKind, MediaMetadata, PlaybackState};
 use mediaremote_rs::{get_now_playing, is_playing, NowPlayingInfo};
-use serde::{Serialize, Deserialize};
 use std::sync::{Mutex, Arc};
 use std::time::{Duration, Instant};
 use base64::{Engine as _, engine::general_purpose};
 
-/// 播放状态信息
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct PlaybackState {
-    /// 是否正在播放
-    pub playing: bool,
-    /// 播放速率 (1.0 = 正常速度)
-    pub playback_rate: f64,
-    /// 已播放时长（秒）
-    pub elapsed_time: f64,
-}
-
-/// 媒体元数据
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct MediaMetadata {
-    /// 应用 Bundle ID
-    pub bundle_identifier: Option<String>,
-    /// 曲目标题
-    pub title: Option<String>,
-    /// 艺术家
-    pub artist: Option<String>,
-    /// 专辑
-    pub album: Option<String>,
-    /// 总时长（秒）
-    pub duration: f64,
-    /// 封面数据 (原始二进制)
-    #[serde(skip)]
-    pub artwork_data: Option<Arc<Vec<u8>>>,
-    /// 封面 MIME 类型
-    pub artwork_mime_type: Option<String>,
-    /// 内容标识符
-    pub content_item_identifier: Option<String>,
-}
-
 /// 媒体信息缓存
 struct MediaCache {
     metadata: Option<MediaMetadata>,
@@ -99,7 +66,8 @@ fn update_cache_from_info(info: &NowPlayingInfo, cache: &mut MediaCache) {
     };
 
     cache.metadata = Some(MediaMetadata {
-        bundle_identifier: if info.bundle_identifier.is_empty() {
+        kind: MediaKind::Unknown,
+        source_app_id: if info.bundle_identifier.is_empty() {
             None
         } else {
             Some(info.bundle_identifier.clone())
@@ -123,11 +91,11 @@ fn update_cache_from_info(info: &NowPlayingInfo, cache: &mut MediaCache) {
         )),
     });
 
-    cache.playback_state = Some(PlaybackState {
-        playing: info.playing,
-        playback_rate: info.playback_rate.unwrap_or(if info.playing { 1.0 } else { 0.0 }),
-        elapsed_time: info.elapsed_time.unwrap_or(0.0),
-    });
+    cache.playback_state = Some(PlaybackState::new(
+        info.playing,
+        info.playback_rate.unwrap_or(if info.playing { 1.0 } else { 0.0 }),
+        info.elapsed_time.unwrap_or(0.0),
+    ));
 
     cache.last_update = Instant::now();
 }
@@ -5,5 +5,53 @@ pub mod media;
 mod window;
 
 pub use accessibility::*;
-pub use media::{MediaMetadata, PlaybackState, get_media_metadata, get_playback_state};
-pub use window::get_frontmost_window_info_sync;
+pub use media::{get_media_metadata, get_playback_state};
+pub use window::{get_app_icon, get_frontmost_window_info_sync};
+
+use super::{MediaMetadata, PlatformProvider, PlaybackState, PollingWatcher, WindowInfo, WindowWatcher};
+
+/// 创建窗口变化监听器（macOS 暂无事件推送，使用轮询）
+pub fn create_window_watcher() -> Box<dyn WindowWatcher> {
+    Box::new(PollingWatcher::new(get_frontmost_window_info_sync))
+}
+
+/// macOS 平台实现
+pub struct MacosProvider;
+
+impl PlatformProvider for MacosProvider {
+    fn name(&self) -> &str {
+        "macos"
+    }
+
+    fn request_permissions(&self) -> Result<bool, String> {
+        request_accessibility_permission()
+    }
+
+    fn check_permissions(&self) -> bool {
+        check_accessibility_permission()
+    }
+
+    fn get_frontmost_window(&self) -> Result<WindowInfo, String> {
+        get_frontmost_window_info_sync()
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        Err("macOS 暂不支持获取窗口列表".to_string())
+    }
+
+    fn create_window_watcher(&self) -> Box<dyn WindowWatcher> {
+        create_window_watcher()
+    }
+
+    fn get_playback_state(&self) -> Result<Option<PlaybackState>, String> {
+        get_playback_state()
+    }
+
+    fn get_media_metadata(&self) -> Result<Option<MediaMetadata>, String> {
+        get_media_metadata()
+    }
+
+    fn get_app_icon(&self, app_id: &str) -> Option<Vec<u8>> {
+        get_app_icon(app_id)
+    }
+}
@@ -3,7 +3,7 @@
 use super::super::WindowInfo;
 use super::check_accessibility_permission;
 use objc2_app_kit::{NSRunningApplication, NSWorkspace, NSBitmapImageRep, NSBitmapImageFileType};
-use objc2_foundation::{NSSize, NSDictionary, NSRect, NSPoint, NSData};
+use objc2_foundation::{NSSize, NSDictionary, NSRect, NSPoint, NSData, NSString};
 use objc2::rc::Retained;
 use objc2::AnyThread; // For alloc
 use core_foundation::base::TCFType;
@@ -109,6 +109,13 @@ pub fn get_frontmost_window_info_sync() -> Result<WindowInfo, String> {
     Ok(info)
 }
 
+/// 按 Bundle ID 获取正在运行的应用图标（带缓存）
+pub fn get_app_icon(bundle_id: &str) -> Option<Vec<u8>> {
+    let apps = NSRunningApplication::runningApplicationsWithBundleIdentifier(&NSString::from_str(bundle_id));
+    let app = apps.firstObject()?;
+    get_cached_app_icon(&app, Some(bundle_id))
+}
+
 /// 带缓存的图标获取
 fn get_cached_app_icon(app: &NSRunningApplication, bundle_id: Option<&str>) -> Option<Vec<u8>> {
     let cache_key = bundle_id.unwrap_or("unknown").to_string();
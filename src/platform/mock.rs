@@ -0,0 +1,412 @@
+//! 脚本化的 mock 平台实现
+//!
+//! 从时间线文件 (JSON / TOML，按扩展名区分) 读取窗口与媒体状态，
+//! 通过 [`PlatformProvider`] 接口按时间依次输出，用于无桌面环境下的集成测试与演示。
+//!
+//! 设置环境变量 `SHIKENMATRIX_MOCK_TIMELINE` 后，[`super::default_provider`] 会使用该实现。
+//!
+//! 时间线示例 (TOML)：
+//!
+//! ```toml
+//! # 可选：每 10 秒从头重放
+//! repeat_after_ms = 10000
+//!
+//! [[events]]
+//! at_ms = 0
+//! window = { title = "README.md - Code", process_name = "code", pid = 100, app_id = "code" }
+//!
+//! [[events]]
+//! at_ms = 3000
+//! window = { title = "YouTube - Firefox", process_name = "firefox", pid = 200 }
+//! media = { title = "Song", artist = "Artist", duration = 180.0, elapsed_time = 12.0, playing = true }
+//! ```
+//!
+//! 每个事件都是完整快照：省略 `window` 表示没有前台窗口，省略 `media` 表示没有媒体在播放。
+
+use super::{
+    unix_millis, MediaKind, MediaMetadata, PlatformProvider, PlaybackState, PollingWatcher, WindowInfo,
+    WindowWatcher,
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 指定时间线文件的环境变量
+pub const MOCK_TIMELINE_ENV: &str = "SHIKENMATRIX_MOCK_TIMELINE";
+
+/// 时间线文件
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+    /// 设置后每隔该时长（毫秒）从头重放
+    pub repeat_after_ms: Option<u64>,
+    /// 状态快照，按 `at_ms` 排序后使用
+    pub events: Vec<TimelineEvent>,
+}
+
+/// 某一时刻的状态快照
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TimelineEvent {
+    /// 相对启动时间（毫秒）
+    pub at_ms: u64,
+    /// 前台窗口
+    pub window: Option<MockWindow>,
+    /// 正在播放的媒体
+    pub media: Option<MockMedia>,
+}
+
+/// 窗口状态
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MockWindow {
+    pub title: String,
+    pub process_name: String,
+    pub pid: i32,
+    pub app_id: Option<String>,
+    /// 图标文件 (PNG)，相对路径基于时间线文件所在目录
+    pub icon_path: Option<PathBuf>,
+}
+
+/// 媒体状态
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MockMedia {
+    pub kind: MediaKind,
+    pub source_app_id: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: f64,
+    /// 事件开始时的播放进度（秒），播放中会随时间推进
+    pub elapsed_time: f64,
+    pub playing: bool,
+    pub playback_rate: f64,
+    /// 封面文件，相对路径基于时间线文件所在目录
+    pub artwork_path: Option<PathBuf>,
+    /// 封面 MIME 类型，省略时根据扩展名判断
+    pub artwork_mime_type: Option<String>,
+    pub content_item_identifier: Option<String>,
+}
+
+impl Default for MockMedia {
+    fn default() -> Self {
+        Self {
+            kind: MediaKind::Unknown,
+            source_app_id: None,
+            title: None,
+            artist: None,
+            album: None,
+            duration: 0.0,
+            elapsed_time: 0.0,
+            playing: true,
+            playback_rate: 1.0,
+            artwork_path: None,
+            artwork_mime_type: None,
+            content_item_identifier: None,
+        }
+    }
+}
+
+/// 预先加载好图标与封面的快照
+struct Frame {
+    at_ms: u64,
+    window: Option<WindowInfo>,
+    media: Option<(MediaMetadata, MockMedia)>,
+}
+
+struct Inner {
+    frames: Vec<Frame>,
+    repeat_after_ms: Option<u64>,
+    started: Instant,
+}
+
+impl Inner {
+    /// 当前时间线上的位置（毫秒）
+    fn position(&self) -> u64 {
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        match self.repeat_after_ms {
+            Some(period) if period > 0 => elapsed % period,
+            _ => elapsed,
+        }
+    }
+
+    /// 当前生效的快照及其已持续时间
+    fn current(&self) -> Option<(&Frame, u64)> {
+        let position = self.position();
+        self.frames
+            .iter()
+            .rev()
+            .find(|frame| frame.at_ms <= position)
+            .map(|frame| (frame, position - frame.at_ms))
+    }
+
+    fn frontmost_window(&self) -> Result<WindowInfo, String> {
+        self.current()
+            .and_then(|(frame, _)| frame.window.clone())
+            .ok_or_else(|| "时间线当前没有前台窗口".to_string())
+    }
+}
+
+/// mock 平台实现
+#[derive(Clone)]
+pub struct MockProvider {
+    inner: Arc<Inner>,
+}
+
+impl MockProvider {
+    /// 从时间线创建，时间从此刻开始计算
+    ///
+    /// `base_dir` 用于解析图标、封面的相对路径
+    pub fn new(timeline: Timeline, base_dir: &Path) -> Result<Self, String> {
+        let mut events = timeline.events;
+        events.sort_by_key(|event| event.at_ms);
+
+        let frames = events
+            .into_iter()
+            .map(|event| {
+                Ok(Frame {
+                    at_ms: event.at_ms,
+                    window: event.window.map(|window| load_window(window, base_dir)).transpose()?,
+                    media: event.media.map(|media| load_media(media, base_dir)).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                frames,
+                repeat_after_ms: timeline.repeat_after_ms,
+                started: Instant::now(),
+            }),
+        })
+    }
+
+    /// 读取时间线文件：`.toml` 按 TOML 解析，其余按 JSON 解析
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取时间线文件失败 {}: {}", path.display(), e))?;
+
+        let timeline: Timeline = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml")) {
+            toml::from_str(&content).map_err(|e| format!("解析时间线失败: {}", e))?
+        } else {
+            serde_json::from_str(&content).map_err(|e| format!("解析时间线失败: {}", e))?
+        };
+
+        Self::new(timeline, path.parent().unwrap_or(Path::new(".")))
+    }
+}
+
+fn resolve(base_dir: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    }
+}
+
+fn read_file(base_dir: &Path, path: &Path) -> Result<Vec<u8>, String> {
+    let path = resolve(base_dir, path);
+    std::fs::read(&path).map_err(|e| format!("读取文件失败 {}: {}", path.display(), e))
+}
+
+fn load_window(window: MockWindow, base_dir: &Path) -> Result<WindowInfo, String> {
+    let icon_data = window
+        .icon_path
+        .as_deref()
+        .map(|path| read_file(base_dir, path))
+        .transpose()?;
+
+    Ok(WindowInfo {
+        title: window.title,
+        icon_data,
+        process_name: window.process_name,
+        pid: window.pid,
+        app_id: window.app_id,
+    })
+}
+
+fn load_media(media: MockMedia, base_dir: &Path) -> Result<(MediaMetadata, MockMedia), String> {
+    let artwork_data = media
+        .artwork_path
+        .as_deref()
+        .map(|path| read_file(base_dir, path).map(Arc::new))
+        .transpose()?;
+
+    let artwork_mime_type = media.artwork_mime_type.clone().or_else(|| {
+        let ext = media.artwork_path.as_deref()?.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some("image/png".to_string()),
+            "jpg" | "jpeg" => Some("image/jpeg".to_string()),
+            "gif" => Some("image/gif".to_string()),
+            "webp" => Some("image/webp".to_string()),
+            _ => None,
+        }
+    });
+
+    // 未指定时生成与其他平台一致的 source:title:album 标识
+    let content_item_identifier = media.content_item_identifier.clone().or_else(|| {
+        Some(format!(
+            "{}:{}:{}",
+            media.source_app_id.as_deref().unwrap_or("mock"),
+            media.title.as_deref().unwrap_or(""),
+            media.album.as_deref().unwrap_or("")
+        ))
+    });
+
+    let metadata = MediaMetadata {
+        kind: media.kind,
+        source_app_id: media.source_app_id.clone(),
+        title: media.title.clone(),
+        artist: media.artist.clone(),
+        album: media.album.clone(),
+        duration: media.duration,
+        artwork_data,
+        artwork_mime_type,
+        content_item_identifier,
+    };
+
+    Ok((metadata, media))
+}
+
+impl PlatformProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn request_permissions(&self) -> Result<bool, String> {
+        Ok(true)
+    }
+
+    fn check_permissions(&self) -> bool {
+        true
+    }
+
+    fn get_frontmost_window(&self) -> Result<WindowInfo, String> {
+        self.inner.frontmost_window()
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        Ok(self.inner.frontmost_window().into_iter().collect())
+    }
+
+    fn create_window_watcher(&self) -> Box<dyn WindowWatcher> {
+        let inner = self.inner.clone();
+        Box::new(PollingWatcher::new(move || inner.frontmost_window()))
+    }
+
+    fn get_playback_state(&self) -> Result<Option<PlaybackState>, String> {
+        Ok(self.inner.current().and_then(|(frame, held_ms)| {
+            let (_, media) = frame.media.as_ref()?;
+            let rate = if media.playing { media.playback_rate } else { 0.0 };
+            let mut elapsed_time = media.elapsed_time + held_ms as f64 / 1000.0 * rate;
+            if media.duration > 0.0 {
+                elapsed_time = elapsed_time.min(media.duration);
+            }
+            Some(PlaybackState {
+                playing: media.playing,
+                playback_rate: rate,
+                elapsed_time,
+                timestamp: unix_millis(),
+            })
+        }))
+    }
+
+    fn get_media_metadata(&self) -> Result<Option<MediaMetadata>, String> {
+        Ok(self
+            .inner
+            .current()
+            .and_then(|(frame, _)| frame.media.as_ref().map(|(metadata, _)| metadata.clone())))
+    }
+
+    fn get_app_icon(&self, app_id: &str) -> Option<Vec<u8>> {
+        self.inner
+            .frames
+            .iter()
+            .filter_map(|frame| frame.window.as_ref())
+            .find(|window| window.app_id.as_deref() == Some(app_id))
+            .and_then(|window| window.icon_data.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 已运行 `elapsed_ms` 的时间线，快照只带媒体或为空
+    fn timeline(frames: Vec<(u64, Option<MockMedia>)>, repeat_after_ms: Option<u64>, elapsed_ms: u64) -> MockProvider {
+        let frames = frames
+            .into_iter()
+            .map(|(at_ms, media)| Frame {
+                at_ms,
+                window: None,
+                media: media.map(|media| load_media(media, Path::new(".")).unwrap()),
+            })
+            .collect();
+        MockProvider {
+            inner: Arc::new(Inner {
+                frames,
+                repeat_after_ms,
+                started: Instant::now() - Duration::from_millis(elapsed_ms),
+            }),
+        }
+    }
+
+    fn empty_frames(at: &[u64]) -> Vec<(u64, Option<MockMedia>)> {
+        at.iter().map(|&at_ms| (at_ms, None)).collect()
+    }
+
+    #[test]
+    fn current_is_the_latest_started_frame() {
+        let provider = timeline(empty_frames(&[0, 1000, 3000]), None, 2500);
+        let (frame, held_ms) = provider.inner.current().unwrap();
+        assert_eq!(frame.at_ms, 1000);
+        assert!((1500..1600).contains(&held_ms));
+    }
+
+    #[test]
+    fn nothing_before_the_first_frame() {
+        let provider = timeline(empty_frames(&[1000]), None, 200);
+        assert!(provider.inner.current().is_none());
+        assert!(provider.get_frontmost_window().is_err());
+    }
+
+    #[test]
+    fn repeat_wraps_the_position() {
+        let provider = timeline(empty_frames(&[0, 1000]), Some(2000), 4500);
+        assert!((500..600).contains(&provider.inner.position()));
+        assert_eq!(provider.inner.current().unwrap().0.at_ms, 0);
+
+        // 0 表示不重放
+        let provider = timeline(empty_frames(&[0, 1000]), Some(0), 4500);
+        assert!(provider.inner.position() >= 4500);
+        assert_eq!(provider.inner.current().unwrap().0.at_ms, 1000);
+    }
+
+    #[test]
+    fn playback_advances_while_playing() {
+        let song = MockMedia { duration: 13.0, elapsed_time: 10.0, ..MockMedia::default() };
+        let state = timeline(vec![(0, Some(song))], None, 2000).get_playback_state().unwrap().unwrap();
+        assert!(state.playing);
+        assert!((12.0..12.1).contains(&state.elapsed_time));
+
+        // 不超过时长
+        let song = MockMedia { duration: 13.0, elapsed_time: 12.0, ..MockMedia::default() };
+        let state = timeline(vec![(0, Some(song))], None, 2000).get_playback_state().unwrap().unwrap();
+        assert_eq!(state.elapsed_time, 13.0);
+
+        let paused = MockMedia { elapsed_time: 10.0, playing: false, ..MockMedia::default() };
+        let state = timeline(vec![(0, Some(paused))], None, 2000).get_playback_state().unwrap().unwrap();
+        assert_eq!((state.playing, state.playback_rate, state.elapsed_time), (false, 0.0, 10.0));
+    }
+
+    #[test]
+    fn generated_content_item_identifier() {
+        let media = MockMedia { title: Some("Song".to_string()), album: Some("Album".to_string()), ..MockMedia::default() };
+        let metadata = timeline(vec![(0, Some(media))], None, 0).get_media_metadata().unwrap().unwrap();
+        assert_eq!(metadata.content_item_identifier.as_deref(), Some("mock:Song:Album"));
+    }
+}
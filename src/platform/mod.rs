@@ -2,6 +2,9 @@
 //! 提供跨平台的窗口和媒体信息获取接口
 
 use serde::{Serialize, Deserialize};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(target_os = "macos")]
 pub mod macos;
@@ -14,6 +17,8 @@ pub mod windows;
 #[cfg(target_os = "linux")]
 pub mod linux;
 
+pub mod mock;
+
 // 重新导出当前平台的实现
 #[cfg(target_os = "macos")]
 #[allow(unused_imports)]
@@ -42,20 +47,252 @@ pub struct WindowInfo {
     pub app_id: Option<String>,
 }
 
+/// 媒体类型
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    /// 无法判断（MediaRemote / MPRIS 不提供该信息）
+    #[default]
+    Unknown,
+    /// 音乐
+    Music,
+    /// 视频
+    Video,
+}
+
+/// 播放状态信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackState {
+    /// 是否正在播放
+    pub playing: bool,
+    /// 播放速率 (1.0 = 正常速度，暂停时为 0.0)
+    pub playback_rate: f64,
+    /// 已播放时长（秒），在 `timestamp` 时刻采样
+    pub elapsed_time: f64,
+    /// `elapsed_time` 的采样时间（Unix 毫秒）
+    pub timestamp: u64,
+}
+
+impl PlaybackState {
+    /// 以当前时间作为采样时间创建
+    pub fn new(playing: bool, playback_rate: f64, elapsed_time: f64) -> Self {
+        Self {
+            playing,
+            playback_rate,
+            elapsed_time,
+            timestamp: unix_millis(),
+        }
+    }
+
+    /// 根据采样时间和播放速率推算 `at`（Unix 毫秒）时刻的播放进度
+    pub fn elapsed_time_at(&self, at: u64) -> f64 {
+        if !self.playing {
+            return self.elapsed_time;
+        }
+        let delta = at.saturating_sub(self.timestamp) as f64 / 1000.0;
+        self.elapsed_time + delta * self.playback_rate
+    }
+}
+
+/// 比较时忽略采样时间，只关心播放状态本身
+impl PartialEq for PlaybackState {
+    fn eq(&self, other: &Self) -> bool {
+        self.playing == other.playing
+            && self.playback_rate == other.playback_rate
+            && self.elapsed_time == other.elapsed_time
+    }
+}
+
+/// 媒体元数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MediaMetadata {
+    /// 媒体类型
+    pub kind: MediaKind,
+    /// 来源应用标识：Bundle ID (macOS)、AUMID (Windows)、DesktopEntry 或 MPRIS bus name 后缀 (Linux)
+    pub source_app_id: Option<String>,
+    /// 曲目标题
+    pub title: Option<String>,
+    /// 艺术家
+    pub artist: Option<String>,
+    /// 专辑
+    pub album: Option<String>,
+    /// 总时长（秒），未知时为 0
+    pub duration: f64,
+    /// 封面数据 (原始二进制)
+    #[serde(skip)]
+    pub artwork_data: Option<Arc<Vec<u8>>>,
+    /// 封面 MIME 类型
+    pub artwork_mime_type: Option<String>,
+    /// 内容标识符，同一曲目保持不变，用于封面缓存
+    pub content_item_identifier: Option<String>,
+}
+
+/// 当前 Unix 时间（毫秒）
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 /// 平台功能 trait
 ///
-/// 注意: PlaybackState 和 MediaMetadata 类型由各平台自行定义
-#[allow(unused)]
-pub trait PlatformProvider {
+/// 对象安全，Reporter 通过 `Box<dyn PlatformProvider>` 使用，
+/// 便于替换为其他后端（指定 X11 / Wayland、mock、回放等）
+pub trait PlatformProvider: Send + Sync {
+    /// 后端名称，用于日志
+    fn name(&self) -> &str;
+
     /// 请求必要的权限
-    fn request_permissions() -> Result<bool, String>;
+    fn request_permissions(&self) -> Result<bool, String>;
 
     /// 检查权限状态
-    fn check_permissions() -> bool;
+    fn check_permissions(&self) -> bool;
 
     /// 获取当前前台窗口信息
-    fn get_frontmost_window() -> Result<WindowInfo, String>;
+    fn get_frontmost_window(&self) -> Result<WindowInfo, String>;
 
     /// 获取所有窗口列表
-    fn get_all_windows() -> Result<Vec<WindowInfo>, String>;
+    fn get_all_windows(&self) -> Result<Vec<WindowInfo>, String>;
+
+    /// 创建窗口变化监听器
+    fn create_window_watcher(&self) -> Box<dyn WindowWatcher>;
+
+    /// 获取当前播放状态
+    fn get_playback_state(&self) -> Result<Option<PlaybackState>, String>;
+
+    /// 获取当前媒体元数据
+    fn get_media_metadata(&self) -> Result<Option<MediaMetadata>, String>;
+
+    /// 按应用标识获取应用图标 (PNG 格式)，不支持时返回 `None`
+    fn get_app_icon(&self, _app_id: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// 默认实现：设置了 `SHIKENMATRIX_MOCK_TIMELINE` 时使用 mock 时间线，否则使用当前平台
+pub fn default_provider() -> Box<dyn PlatformProvider> {
+    if let Some(path) = std::env::var_os(mock::MOCK_TIMELINE_ENV) {
+        match mock::MockProvider::from_file(&path) {
+            Ok(provider) => {
+                tracing::info!("使用 mock 时间线: {}", std::path::Path::new(&path).display());
+                return Box::new(provider);
+            }
+            Err(e) => tracing::warn!("加载 mock 时间线失败，使用当前平台实现: {}", e),
+        }
+    }
+    native_provider()
+}
+
+#[cfg(target_os = "macos")]
+fn native_provider() -> Box<dyn PlatformProvider> {
+    Box::new(macos::MacosProvider)
+}
+
+#[cfg(target_os = "windows")]
+fn native_provider() -> Box<dyn PlatformProvider> {
+    Box::new(windows::WindowsProvider)
+}
+
+/// 运行时检测 Wayland / X11
+#[cfg(target_os = "linux")]
+fn native_provider() -> Box<dyn PlatformProvider> {
+    Box::new(linux::LinuxProvider::new())
+}
+
+/// 窗口变化事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// 前台窗口切换
+    FocusChanged,
+    /// 前台窗口标题变化
+    TitleChanged,
+}
+
+/// 窗口变化监听器
+///
+/// 能推送事件的后端（X11 PropertyNotify、Wayland toplevel 事件）通过 [`ChannelWatcher`] 实现，
+/// 其余后端使用 [`PollingWatcher`] 定时查询
+pub trait WindowWatcher: Send {
+    /// 等待下一个窗口变化事件，超时返回 `None`
+    fn next_event(&mut self, timeout: Duration) -> Option<WindowEvent>;
+}
+
+/// 前台窗口查询函数
+pub type FrontmostWindowFn = Box<dyn FnMut() -> Result<WindowInfo, String> + Send>;
+
+/// 轮询实现：每次等待 `timeout` 后查询一次前台窗口并与上次结果比较
+pub struct PollingWatcher {
+    query: FrontmostWindowFn,
+    /// 上次查询结果：`Some((pid, app_id, title))`，查询失败为 `None`
+    last: Option<Option<(i32, Option<String>, String)>>,
+}
+
+impl PollingWatcher {
+    pub fn new(query: impl FnMut() -> Result<WindowInfo, String> + Send + 'static) -> Self {
+        Self { query: Box::new(query), last: None }
+    }
+}
+
+impl WindowWatcher for PollingWatcher {
+    fn next_event(&mut self, timeout: Duration) -> Option<WindowEvent> {
+        // 首次调用立即查询，保证调用方能拿到初始状态
+        if self.last.is_some() {
+            std::thread::sleep(timeout);
+        }
+
+        let current = (self.query)().ok().map(|info| (info.pid, info.app_id, info.title));
+        let previous = self.last.replace(current.clone());
+
+        match (previous, current) {
+            (None, _) => Some(WindowEvent::FocusChanged),
+            (Some(Some((old_pid, old_app, old_title))), Some((pid, app, title))) => {
+                if old_pid != pid || old_app != app {
+                    Some(WindowEvent::FocusChanged)
+                } else if old_title != title {
+                    Some(WindowEvent::TitleChanged)
+                } else {
+                    None
+                }
+            }
+            // 查询成功/失败状态切换时也通知调用方，便于重新获取并记录错误
+            (Some(old), new) => (old.is_some() != new.is_some()).then_some(WindowEvent::FocusChanged),
+        }
+    }
+}
+
+/// 事件推送实现：从后端事件线程接收事件，事件源断开后回退到轮询
+pub struct ChannelWatcher {
+    events: Receiver<WindowEvent>,
+    fallback: PollingWatcher,
+    started: bool,
+}
+
+impl ChannelWatcher {
+    pub fn new(
+        events: Receiver<WindowEvent>,
+        query: impl FnMut() -> Result<WindowInfo, String> + Send + 'static,
+    ) -> Self {
+        Self {
+            events,
+            fallback: PollingWatcher::new(query),
+            started: false,
+        }
+    }
+}
+
+impl WindowWatcher for ChannelWatcher {
+    fn next_event(&mut self, timeout: Duration) -> Option<WindowEvent> {
+        // 首次调用立即返回，保证调用方能拿到初始状态
+        if !self.started {
+            self.started = true;
+            return Some(WindowEvent::FocusChanged);
+        }
+
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => self.fallback.next_event(timeout),
+        }
+    }
 }
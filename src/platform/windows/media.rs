@@ -1,8 +1,10 @@
 //! Windows 媒体播放信息获取模块
 //! 基于 Windows.Media.Control (SMTC)
 
-use serde::{Serialize, Deserialize};
+use super::super::{MediaKind, MediaMetadata, PlaybackState};
+use std::sync::Arc;
 use windows::core::{Result, HSTRING};
+use windows::Media::MediaPlaybackType;
 use windows::Media::Control::{
     GlobalSystemMediaTransportControlsSessionManager,
     GlobalSystemMediaTransportControlsSessionPlaybackInfo,
@@ -11,40 +13,6 @@ use windows::Media::Control::{
 };
 use windows::Storage::Streams::DataReader;
 use tokio::runtime::Runtime;
-use base64::Engine;
-use base64::engine::general_purpose::STANDARD as BASE64;
-
-/// 播放状态信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlaybackState {
-    /// 是否正在播放
-    pub playing: bool,
-    /// 播放速率 (1.0 = 正常速度)
-    pub playback_rate: f64,
-    /// 已播放时长（秒）
-    pub elapsed_time: f64,
-}
-
-/// 媒体元数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MediaMetadata {
-    /// 应用 Bundle ID (Windows 上为 AUMID)
-    pub bundle_identifier: Option<String>,
-    /// 曲目标题
-    pub title: Option<String>,
-    /// 艺术家
-    pub artist: Option<String>,
-    /// 专辑
-    pub album: Option<String>,
-    /// 总时长（秒）
-    pub duration: f64,
-    /// 封面数据 (Base64 编码)
-    pub artwork_data: Option<String>,
-    /// 封面 MIME 类型
-    pub artwork_mime_type: Option<String>,
-    /// 内容标识符
-    pub content_item_identifier: Option<String>,
-}
 
 /// 获取当前播放状态
 pub fn get_playback_state() -> std::result::Result<Option<PlaybackState>, String> {
@@ -129,8 +97,7 @@ async fn get_smtc_info() -> Result<Option<(MediaMetadata, PlaybackState)>> {
                     if let Ok(_) = data_reader.LoadAsync(stream_size_u32)?.get() {
                         let mut buffer = vec![0u8; stream_size_u32 as usize];
                         if let Ok(_) = data_reader.ReadBytes(&mut buffer) {
-                             // 转换为 Base64
-                             artwork_data = Some(BASE64.encode(&buffer));
+                             artwork_data = Some(Arc::new(buffer));
                              // 简单猜测 MIME，通常是 PNG 或 JPEG，这里假设是 PNG 因为参考代码保存为 .png
                              // 但实际上可能是 jpg。Windows Thumbnail 流通常有 ContentType 属性，但这里简化处理
                              artwork_mime_type = Some("image/png".to_string());
@@ -141,8 +108,16 @@ async fn get_smtc_info() -> Result<Option<(MediaMetadata, PlaybackState)>> {
         }
     }
 
+    // 媒体类型，未提供时为 Unknown
+    let kind = match media_properties.PlaybackType().and_then(|t| t.Value()) {
+        Ok(MediaPlaybackType::Music) => MediaKind::Music,
+        Ok(MediaPlaybackType::Video) => MediaKind::Video,
+        _ => MediaKind::Unknown,
+    };
+
     let metadata = MediaMetadata {
-        bundle_identifier: Some(source_app_name_hstring.to_string_lossy()),
+        kind,
+        source_app_id: Some(source_app_name_hstring.to_string_lossy()),
         title: Some(title_hstring.to_string_lossy()),
         artist: Some(artist_hstring.to_string_lossy()),
         album: Some(album_hstring.to_string_lossy()),
@@ -152,11 +127,11 @@ async fn get_smtc_info() -> Result<Option<(MediaMetadata, PlaybackState)>> {
         content_item_identifier: None,
     };
 
-    let state = PlaybackState {
-        playing: is_playing,
-        playback_rate: 1.0, // 简化处理，假设为 1.0
+    let state = PlaybackState::new(
+        is_playing,
+        if is_playing { 1.0 } else { 0.0 }, // 简化处理，假设为 1.0
         elapsed_time,
-    };
+    );
 
     Ok(Some((metadata, state)))
 }
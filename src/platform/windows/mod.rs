@@ -3,9 +3,11 @@
 pub mod media;
 pub mod window;
 
-pub use media::{get_media_metadata, get_playback_state, MediaMetadata, PlaybackState};
+pub use media::{get_media_metadata, get_playback_state};
 pub use window::{get_frontmost_window, apply_vibrancy, get_all_windows};
 
+use super::{MediaMetadata, PlatformProvider, PlaybackState, PollingWatcher, WindowInfo, WindowWatcher};
+
 /// 请求必要的权限 (Windows 通常不需要像 macOS 那样显式请求权限)
 pub fn request_permissions() -> Result<bool, String> {
     Ok(true)
@@ -15,3 +17,45 @@ pub fn request_permissions() -> Result<bool, String> {
 pub fn check_permissions() -> bool {
     true
 }
+
+/// 创建窗口变化监听器（Windows 暂无事件推送，使用轮询）
+pub fn create_window_watcher() -> Box<dyn WindowWatcher> {
+    Box::new(PollingWatcher::new(get_frontmost_window))
+}
+
+/// Windows 平台实现
+pub struct WindowsProvider;
+
+impl PlatformProvider for WindowsProvider {
+    fn name(&self) -> &str {
+        "windows"
+    }
+
+    fn request_permissions(&self) -> Result<bool, String> {
+        request_permissions()
+    }
+
+    fn check_permissions(&self) -> bool {
+        check_permissions()
+    }
+
+    fn get_frontmost_window(&self) -> Result<WindowInfo, String> {
+        get_frontmost_window()
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        get_all_windows()
+    }
+
+    fn create_window_watcher(&self) -> Box<dyn WindowWatcher> {
+        create_window_watcher()
+    }
+
+    fn get_playback_state(&self) -> Result<Option<PlaybackState>, String> {
+        get_playback_state()
+    }
+
+    fn get_media_metadata(&self) -> Result<Option<MediaMetadata>, String> {
+        get_media_metadata()
+    }
+}
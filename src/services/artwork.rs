@@ -0,0 +1,369 @@
+//! Artwork URLs handed out by a server
+//! Images are identified by the SHA-256 of their bytes, so tracks sharing a cover share one upload
+//!
+//! The URLs are kept in ~/.shikenmatrix/artwork.json (`artwork-<endpoint>.json` for extra endpoints)
+//! so a restart doesn't upload every cover again. Entries expire after `ttl_days`; beyond
+//! `max_entries` the least recently used one is dropped. Changes are written at most once a
+//! minute, the rest when the reporter stops.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::platform::unix_millis;
+use super::reporter::DEFAULT_ENDPOINT;
+
+const CACHE_FILE: &str = "artwork.json";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Minimum time between two writes of the cache file
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lowercase hex SHA-256 of the image bytes
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Artwork URL cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ArtworkCacheConfig {
+    /// Keep URLs on disk across restarts
+    pub enabled: bool,
+    /// Upper bound on cached tracks
+    pub max_entries: usize,
+    /// Forget URLs older than this, 0 keeps them until evicted
+    pub ttl_days: u64,
+}
+
+impl Default for ArtworkCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 500,
+            ttl_days: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedUrl {
+    url: String,
+    /// Content hash, unknown when the upload wasn't tracked
+    #[serde(default)]
+    hash: Option<String>,
+    /// Unix milliseconds when the server handed out the URL
+    stored_at: u64,
+    /// Unix milliseconds of the last upload this entry made unnecessary
+    used_at: u64,
+}
+
+/// On-disk layout
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// Server the URLs belong to; another server starts from scratch
+    server: String,
+    /// `content_item_identifier` → URL
+    entries: HashMap<String, CachedUrl>,
+}
+
+/// URLs of uploaded artwork, by track and by image
+#[derive(Debug, Default)]
+pub struct ArtworkUrls {
+    config: ArtworkCacheConfig,
+    path: Option<PathBuf>,
+    server: String,
+    entries: HashMap<String, CachedUrl>,
+    /// Changes not written to the file yet
+    dirty: bool,
+    saved_at: Option<Instant>,
+}
+
+/// Cache file for an endpoint; the default endpoint gets the plain file name
+fn get_cache_path(endpoint: &str) -> Option<PathBuf> {
+    let dir = dirs::home_dir()?.join(".shikenmatrix");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok()?;
+    }
+    if endpoint == DEFAULT_ENDPOINT {
+        return Some(dir.join(CACHE_FILE));
+    }
+    let name: String = endpoint
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Some(dir.join(format!("artwork-{}.json", name)))
+}
+
+impl ArtworkUrls {
+    /// Open the cache of `endpoint`, keeping what an earlier run learned from the same `server`
+    pub fn open(config: ArtworkCacheConfig, endpoint: &str, server: &str) -> Self {
+        let path = if config.enabled { get_cache_path(endpoint) } else { None };
+
+        let mut entries = HashMap::new();
+        if let Some(content) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            match serde_json::from_str::<CacheFile>(&content) {
+                Ok(file) if file.server == server => entries = file.entries,
+                Ok(_) => info!("Artwork cache of '{}' belongs to another server, starting over", endpoint),
+                Err(e) => warn!("Ignoring corrupt artwork cache: {}", e),
+            }
+        }
+
+        let mut cache = Self { config, path, server: server.to_string(), entries, ..Self::default() };
+        let loaded = cache.entries.len();
+        // Config may have changed since the file was written
+        if cache.trim() {
+            cache.dirty = true;
+            cache.flush();
+        }
+        if loaded > 0 {
+            info!("Loaded {} cached artwork URLs for '{}'", cache.entries.len(), endpoint);
+        }
+        cache
+    }
+
+    /// URL of the artwork of a track, which counts as a use
+    pub fn get(&mut self, content_item_identifier: &str) -> Option<&str> {
+        let now = unix_millis();
+        if self.entries.get(content_item_identifier).is_none_or(|entry| self.is_expired(entry, now)) {
+            return None;
+        }
+        if let Some(entry) = self.entries.get_mut(content_item_identifier) {
+            entry.used_at = now;
+        }
+        self.changed();
+        self.entries.get(content_item_identifier).map(|entry| entry.url.as_str())
+    }
+
+    /// Record an acknowledged upload; `hash` is unknown when the upload wasn't tracked
+    pub fn insert(&mut self, content_item_identifier: String, hash: Option<String>, url: String) {
+        let now = unix_millis();
+        self.entries.insert(content_item_identifier, CachedUrl { url, hash, stored_at: now, used_at: now });
+        self.trim();
+        self.changed();
+    }
+
+    /// URL for a track, falling back to the same image uploaded for another track
+    pub fn resolve(&mut self, content_item_identifier: &str, hash: &str) -> Option<String> {
+        let now = unix_millis();
+        let entry = match self.entries.get(content_item_identifier) {
+            Some(entry) if !self.is_expired(entry, now) => entry.clone(),
+            _ => self
+                .entries
+                .values()
+                .filter(|entry| entry.hash.as_deref() == Some(hash) && !self.is_expired(entry, now))
+                .max_by_key(|entry| entry.stored_at)?
+                .clone(),
+        };
+        // Expires together with the upload it points to
+        self.entries.insert(content_item_identifier.to_string(), CachedUrl { used_at: now, ..entry.clone() });
+        self.trim();
+        self.changed();
+        Some(entry.url)
+    }
+
+    /// Forget an image the server no longer has, under every track that used it
+    ///
+    /// Any of the identifiers is enough. Returns the tracks that lost their URL.
+    pub fn invalidate(&mut self, content_item_identifier: Option<&str>, hash: Option<&str>, url: Option<&str>) -> Vec<String> {
+        let mut urls: Vec<String> = url.into_iter().map(str::to_string).collect();
+        let mut hashes: Vec<String> = hash.into_iter().map(str::to_string).collect();
+        if let Some(entry) = content_item_identifier.and_then(|id| self.entries.get(id)) {
+            urls.push(entry.url.clone());
+            hashes.extend(entry.hash.clone());
+        }
+
+        let removed: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(id, entry)| {
+                Some(id.as_str()) == content_item_identifier
+                    || urls.contains(&entry.url)
+                    || entry.hash.as_ref().is_some_and(|hash| hashes.contains(hash))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        if !removed.is_empty() {
+            for id in &removed {
+                self.entries.remove(id);
+            }
+            self.changed();
+        }
+        removed
+    }
+
+    fn is_expired(&self, entry: &CachedUrl, now: u64) -> bool {
+        self.config.ttl_days > 0 && now.saturating_sub(entry.stored_at) > self.config.ttl_days.saturating_mul(DAY_MS)
+    }
+
+    /// Drop expired entries, then the least recently used beyond `max_entries`; returns whether anything was dropped
+    fn trim(&mut self) -> bool {
+        let before = self.entries.len();
+        let now = unix_millis();
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.is_expired(entry, now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in expired {
+            self.entries.remove(&id);
+        }
+
+        let max_entries = self.config.max_entries.max(1);
+        if self.entries.len() > max_entries {
+            let mut by_use: Vec<(u64, String)> =
+                self.entries.iter().map(|(id, entry)| (entry.used_at, id.clone())).collect();
+            by_use.sort_unstable();
+            for (_, id) in by_use.into_iter().take(self.entries.len() - max_entries) {
+                self.entries.remove(&id);
+            }
+        }
+        self.entries.len() != before
+    }
+
+    /// Note a change, writing the file unless that happened within `SAVE_INTERVAL`
+    fn changed(&mut self) {
+        self.dirty = true;
+        if self.saved_at.is_none_or(|saved_at| saved_at.elapsed() >= SAVE_INTERVAL) {
+            self.flush();
+        }
+    }
+
+    /// Write changes that are still pending
+    pub fn flush(&mut self) {
+        if !std::mem::take(&mut self.dirty) {
+            return;
+        }
+        self.saved_at = Some(Instant::now());
+        let Some(path) = &self.path else { return };
+        let file = CacheFile { server: self.server.clone(), entries: self.entries.clone() };
+        let result = serde_json::to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to write artwork cache: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Removes the cache file at the end of a test
+    struct TempFile(PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Cache backed by a file of its own under the temp directory
+    fn cache(max_entries: usize) -> (ArtworkUrls, TempFile) {
+        let path = std::env::temp_dir().join(format!("shikenmatrix-artwork-{}-{:08x}.json", std::process::id(), fastrand::u32(..)));
+        let config = ArtworkCacheConfig { enabled: true, max_entries, ttl_days: 30 };
+        let server = "wss://example.com/ws".to_string();
+        (ArtworkUrls { config, path: Some(path.clone()), server, ..ArtworkUrls::default() }, TempFile(path))
+    }
+
+    fn on_disk(cache: &ArtworkUrls) -> HashMap<String, CachedUrl> {
+        let content = fs::read_to_string(cache.path.as_ref().unwrap()).unwrap_or_default();
+        serde_json::from_str::<CacheFile>(&content).map(|file| file.entries).unwrap_or_default()
+    }
+
+    #[test]
+    fn content_hash_is_hex_sha256() {
+        assert_eq!(content_hash(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn get_counts_as_a_use() {
+        let (mut cache, _file) = cache(2);
+        cache.insert("a".to_string(), None, "https://cdn/a.png".to_string());
+        cache.insert("b".to_string(), None, "https://cdn/b.png".to_string());
+        cache.entries.get_mut("a").unwrap().used_at -= 1000;
+        cache.entries.get_mut("b").unwrap().used_at -= 500;
+
+        // Reading `a` makes `b` the least recently used one
+        assert_eq!(cache.get("a"), Some("https://cdn/a.png"));
+        cache.insert("c".to_string(), None, "https://cdn/c.png".to_string());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some() && cache.get("c").is_some());
+    }
+
+    #[test]
+    fn expired_entries_are_not_returned() {
+        let (mut cache, _file) = cache(10);
+        cache.insert("a".to_string(), None, "https://cdn/a.png".to_string());
+        cache.entries.get_mut("a").unwrap().stored_at -= 31 * DAY_MS;
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.resolve("a", "hash"), None);
+    }
+
+    #[test]
+    fn resolve_falls_back_to_the_same_image() {
+        let (mut cache, _file) = cache(10);
+        cache.insert("a".to_string(), Some("hash".to_string()), "https://cdn/a.png".to_string());
+        assert_eq!(cache.resolve("b", "hash").as_deref(), Some("https://cdn/a.png"));
+        assert_eq!(cache.get("b"), Some("https://cdn/a.png"));
+        assert_eq!(cache.resolve("c", "other"), None);
+
+        // Forgetting the image forgets it under both tracks
+        let mut removed = cache.invalidate(Some("a"), None, None);
+        removed.sort();
+        assert_eq!(removed, ["a", "b"]);
+        assert!(cache.get("b").is_none());
+    }
+
+    #[test]
+    fn fallback_stays_within_max_entries() {
+        let (mut cache, _file) = cache(3);
+        cache.insert("a".to_string(), Some("hash".to_string()), "https://cdn/a.png".to_string());
+        for track in 1..=5 {
+            let track = format!("track-{}", track);
+            assert_eq!(cache.resolve(&track, "hash").as_deref(), Some("https://cdn/a.png"), "{}", track);
+            assert!(cache.entries.len() <= 3, "{} entries after {}", cache.entries.len(), track);
+        }
+        assert_eq!(cache.get("track-5"), Some("https://cdn/a.png"));
+        cache.flush();
+        assert_eq!(on_disk(&cache).len(), 3);
+    }
+
+    #[test]
+    fn lookups_do_not_rewrite_the_file() {
+        let (mut cache, _file) = cache(10);
+        cache.insert("a".to_string(), Some("hash".to_string()), "https://cdn/a.png".to_string());
+        let written = on_disk(&cache);
+        assert_eq!(written["a"].url, "https://cdn/a.png");
+
+        // Within the save interval changes only mark the cache dirty
+        cache.entries.get_mut("a").unwrap().used_at -= 1000;
+        assert!(cache.resolve("b", "hash").is_some());
+        assert!(cache.get("a").is_some());
+        assert!(cache.dirty);
+        let unchanged = on_disk(&cache);
+        assert!(!unchanged.contains_key("b"));
+        assert_eq!(unchanged["a"].used_at, written["a"].used_at);
+
+        cache.flush();
+        assert!(!cache.dirty);
+        let flushed = on_disk(&cache);
+        assert_eq!(flushed["b"].url, "https://cdn/a.png");
+        assert!(flushed["a"].used_at >= written["a"].used_at);
+    }
+
+    #[test]
+    fn writes_again_after_the_interval() {
+        let (mut cache, _file) = cache(10);
+        cache.insert("a".to_string(), None, "https://cdn/a.png".to_string());
+        cache.saved_at = Instant::now().checked_sub(SAVE_INTERVAL);
+        cache.insert("b".to_string(), None, "https://cdn/b.png".to_string());
+        assert!(!cache.dirty);
+        assert!(on_disk(&cache).contains_key("b"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Message encodings for the WebSocket transport
+//! JSON goes out as Text frames; MessagePack and CBOR as self-describing Binary frames
+//!
+//! The codec is negotiated per connection: `hello` lists what the client accepts, `welcome`
+//! names the server's pick. `hello` and `welcome` themselves are always JSON.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    #[default]
+    Json,
+    Msgpack,
+    Cbor,
+}
+
+impl Codec {
+    /// Codecs to offer in `hello`, most preferred first; JSON is always accepted
+    pub fn offer(preferred: Codec) -> Vec<Codec> {
+        if preferred == Codec::Json {
+            vec![Codec::Json]
+        } else {
+            vec![preferred, Codec::Json]
+        }
+    }
+
+    /// Artwork and its metadata fit in a single frame
+    pub fn is_binary(&self) -> bool {
+        *self != Codec::Json
+    }
+
+    /// Frame carrying `value`
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Message, String> {
+        match self {
+            Codec::Json => serde_json::to_string(value).map(|json| Message::Text(json.into())).map_err(|e| e.to_string()),
+            Codec::Msgpack => rmp_serde::to_vec_named(value)
+                .map(|bytes| Message::Binary(bytes.into()))
+                .map_err(|e| format!("MessagePack encoding failed: {}", e)),
+            Codec::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|e| format!("CBOR encoding failed: {}", e))?;
+                Ok(Message::Binary(bytes.into()))
+            }
+        }
+    }
+
+    /// Decode the payload of a Binary frame
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Codec::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            Codec::Msgpack => rmp_serde::from_slice(bytes).map_err(|e| format!("MessagePack decoding failed: {}", e)),
+            Codec::Cbor => ciborium::from_reader(bytes).map_err(|e| format!("CBOR decoding failed: {}", e)),
+        }
+    }
+}
+
+/// Artwork and its metadata in one frame, only sent with a binary codec
+#[derive(Debug, Serialize)]
+pub struct UploadArtworkMessage<'a> {
+    #[serde(rename = "type")]
+    pub msg_type: &'static str,
+    pub content_item_identifier: &'a str,
+    /// SHA-256 of `data`, hex
+    pub hash: &'a str,
+    pub mime_type: &'a str,
+    #[serde(with = "serde_bytes")]
+    pub data: &'a [u8],
+}
+
+impl<'a> UploadArtworkMessage<'a> {
+    pub fn new(content_item_identifier: &'a str, hash: &'a str, mime_type: &'a str, data: &'a [u8]) -> Self {
+        Self {
+            msg_type: "upload_artwork",
+            content_item_identifier,
+            hash,
+            mime_type,
+            data,
+        }
+    }
+}
+
+/// Asks whether the server already has an image, answered with `has_artwork_result`
+#[derive(Debug, Serialize)]
+pub struct HasArtworkMessage<'a> {
+    #[serde(rename = "type")]
+    pub msg_type: &'static str,
+    pub content_item_identifier: &'a str,
+    /// SHA-256 of the image, hex
+    pub hash: &'a str,
+}
+
+impl<'a> HasArtworkMessage<'a> {
+    pub fn new(content_item_identifier: &'a str, hash: &'a str) -> Self {
+        Self {
+            msg_type: "has_artwork",
+            content_item_identifier,
+            hash,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODECS: [Codec; 3] = [Codec::Json, Codec::Msgpack, Codec::Cbor];
+
+    /// Owned mirror of `UploadArtworkMessage`, as a server would read it
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct UploadArtwork {
+        #[serde(rename = "type")]
+        msg_type: String,
+        content_item_identifier: String,
+        hash: String,
+        mime_type: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct HasArtwork {
+        #[serde(rename = "type")]
+        msg_type: String,
+        content_item_identifier: String,
+        hash: String,
+    }
+
+    /// Frame bytes, checking the frame type matches the codec
+    fn payload(codec: Codec, frame: Message) -> Vec<u8> {
+        match frame {
+            Message::Text(text) if !codec.is_binary() => text.as_bytes().to_vec(),
+            Message::Binary(bytes) if codec.is_binary() => bytes.to_vec(),
+            other => panic!("{:?} produced {:?}", codec, other),
+        }
+    }
+
+    #[test]
+    fn offer_always_includes_json() {
+        assert_eq!(Codec::offer(Codec::Json), [Codec::Json]);
+        assert_eq!(Codec::offer(Codec::Cbor), [Codec::Cbor, Codec::Json]);
+    }
+
+    #[test]
+    fn upload_artwork_round_trips() {
+        // Every byte value, so an encoding as an integer array would show
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        for codec in CODECS {
+            let message = UploadArtworkMessage::new("track-1", "abc123", "image/png", &data);
+            let bytes = payload(codec, codec.encode(&message).unwrap());
+            let decoded: UploadArtwork = codec.decode(&bytes).unwrap();
+            assert_eq!(
+                decoded,
+                UploadArtwork {
+                    msg_type: "upload_artwork".to_string(),
+                    content_item_identifier: "track-1".to_string(),
+                    hash: "abc123".to_string(),
+                    mime_type: "image/png".to_string(),
+                    data: data.clone(),
+                },
+                "{:?}",
+                codec
+            );
+
+            // Binary codecs carry the image as a byte string
+            if codec.is_binary() {
+                assert!(bytes.windows(data.len()).any(|window| window == data), "{:?}", codec);
+                assert!(bytes.len() < data.len() + 128, "{:?}: {} bytes", codec, bytes.len());
+            }
+        }
+    }
+
+    #[test]
+    fn has_artwork_round_trips() {
+        for codec in CODECS {
+            let message = HasArtworkMessage::new("track-1", "abc123");
+            let decoded: HasArtwork = codec.decode(&payload(codec, codec.encode(&message).unwrap())).unwrap();
+            let expected = HasArtwork {
+                msg_type: "has_artwork".to_string(),
+                content_item_identifier: "track-1".to_string(),
+                hash: "abc123".to_string(),
+            };
+            assert_eq!(decoded, expected, "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn json_values_round_trip() {
+        let value = serde_json::json!({ "type": "media_playback", "metadata": { "title": "曲名", "duration": 215.5, "artwork_url": null } });
+        for codec in CODECS {
+            let decoded: serde_json::Value = codec.decode(&payload(codec, codec.encode(&value).unwrap())).unwrap();
+            assert_eq!(decoded, value, "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        for codec in CODECS {
+            assert!(codec.decode::<HasArtwork>(&[0xc1, 0xff, 0x00]).is_err(), "{:?}", codec);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Server-to-client commands over the WebSocket transport
+//! `{"type": "command", "id": "42", "command": "pause_reporting"}`, arguments next to `command`
+//!
+//! Every command is answered with `{"type": "command_result", "id": "42", "ok": true}`, or
+//! `"ok": false` and an `error` when it was rejected. Pausing and filter changes apply to the
+//! endpoint that sent them and last until it reconnects; `set_poll_interval` changes the
+//! monitoring loop shared by all endpoints.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::ffi::CString;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use super::reporter::CommandCallback;
+
+/// Interval of the window/media polling loop until a server changes it
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 1000;
+/// Range accepted by `set_poll_interval`
+const POLL_INTERVAL_RANGE_MS: std::ops::RangeInclusive<u64> = 250..=60_000;
+
+/// A `command` message as received
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerCommand {
+    pub id: String,
+    pub command: String,
+    /// Remaining fields, the command's arguments
+    #[serde(flatten)]
+    pub args: Map<String, Value>,
+}
+
+/// Commands this client understands
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Resend the current window, media and pending artwork
+    RequestSnapshot,
+    /// Stop sending updates; they are dropped, not queued
+    PauseReporting,
+    /// Send updates again, starting with a snapshot
+    ResumeReporting,
+    /// Override the endpoint filter; omitted kinds keep their current setting
+    UpdateFilters {
+        #[serde(default)]
+        window: Option<bool>,
+        #[serde(default)]
+        media: Option<bool>,
+    },
+    /// Change how often windows and media are polled
+    SetPollInterval { interval_ms: u64 },
+    /// Upload artwork again, the current track's when no identifier is given
+    ReuploadArtwork {
+        #[serde(default)]
+        content_item_identifier: Option<String>,
+    },
+}
+
+impl ServerCommand {
+    /// Typed command, or why it was rejected
+    pub fn parse(&self) -> Result<Command, String> {
+        let mut fields = self.args.clone();
+        fields.insert("command".to_string(), Value::String(self.command.clone()));
+        serde_json::from_value(Value::Object(fields)).map_err(|e| format!("Invalid command '{}': {}", self.command, e))
+    }
+}
+
+/// Answer to a `command`
+#[derive(Debug, Serialize)]
+pub struct CommandResultMessage<'a> {
+    #[serde(rename = "type")]
+    pub msg_type: &'static str,
+    pub id: &'a str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+}
+
+impl<'a> CommandResultMessage<'a> {
+    pub fn new(id: &'a str, result: &'a Result<(), String>) -> Self {
+        Self {
+            msg_type: "command_result",
+            id,
+            ok: result.is_ok(),
+            error: result.as_ref().err().map(String::as_str),
+        }
+    }
+}
+
+/// Reporter state that commands reach beyond their own connection
+#[derive(Clone)]
+pub(super) struct CommandContext {
+    callback: Arc<RwLock<CommandCallback>>,
+    user_data: Arc<AtomicUsize>,
+    poll_interval_ms: Arc<AtomicU64>,
+}
+
+impl CommandContext {
+    pub(super) fn new(
+        callback: Arc<RwLock<CommandCallback>>,
+        user_data: Arc<AtomicUsize>,
+        poll_interval_ms: Arc<AtomicU64>,
+    ) -> Self {
+        Self { callback, user_data, poll_interval_ms }
+    }
+
+    pub(super) fn set_poll_interval(&self, interval_ms: u64) -> Result<(), String> {
+        if !POLL_INTERVAL_RANGE_MS.contains(&interval_ms) {
+            return Err(format!(
+                "interval_ms must be between {} and {}",
+                POLL_INTERVAL_RANGE_MS.start(),
+                POLL_INTERVAL_RANGE_MS.end()
+            ));
+        }
+        self.poll_interval_ms.store(interval_ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Tell the frontend a command was handled
+    pub(super) fn notify(&self, endpoint: &str, command: &ServerCommand, ok: bool) {
+        let Ok(callback) = self.callback.read() else { return };
+        let Some(cb) = *callback else { return };
+        let user_data = self.user_data.load(Ordering::Relaxed);
+        let c_endpoint = CString::new(endpoint).unwrap_or_default();
+        let c_id = CString::new(command.id.as_str()).unwrap_or_default();
+        let c_command = CString::new(command.command.as_str()).unwrap_or_default();
+        let c_args = CString::new(Value::Object(command.args.clone()).to_string()).unwrap_or_default();
+        cb(c_endpoint.as_ptr(), c_id.as_ptr(), c_command.as_ptr(), c_args.as_ptr(), ok, user_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn command(message: Value) -> ServerCommand {
+        serde_json::from_value(message).unwrap()
+    }
+
+    fn context() -> CommandContext {
+        CommandContext::new(
+            Arc::new(RwLock::new(None)),
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicU64::new(DEFAULT_POLL_INTERVAL_MS)),
+        )
+    }
+
+    #[test]
+    fn parses_known_commands() {
+        let parsed = |message: Value| command(message).parse().unwrap();
+        assert_eq!(parsed(json!({ "id": "1", "command": "request_snapshot" })), Command::RequestSnapshot);
+        assert_eq!(parsed(json!({ "id": "2", "command": "pause_reporting" })), Command::PauseReporting);
+        assert_eq!(
+            parsed(json!({ "id": "3", "command": "update_filters", "media": false })),
+            Command::UpdateFilters { window: None, media: Some(false) }
+        );
+        assert_eq!(
+            parsed(json!({ "id": "4", "command": "set_poll_interval", "interval_ms": 500 })),
+            Command::SetPollInterval { interval_ms: 500 }
+        );
+        assert_eq!(
+            parsed(json!({ "id": "5", "command": "reupload_artwork" })),
+            Command::ReuploadArtwork { content_item_identifier: None }
+        );
+    }
+
+    #[test]
+    fn arguments_are_kept_for_the_callback() {
+        let cmd = command(json!({ "id": "7", "command": "update_filters", "window": true }));
+        assert_eq!(cmd.id, "7");
+        assert_eq!(Value::Object(cmd.args), json!({ "window": true }));
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_bad_arguments() {
+        for (message, expected) in [
+            (json!({ "id": "1", "command": "reboot" }), "Invalid command 'reboot'"),
+            (json!({ "id": "2", "command": "set_poll_interval" }), "interval_ms"),
+            (json!({ "id": "3", "command": "set_poll_interval", "interval_ms": "fast" }), "Invalid command"),
+        ] {
+            let e = command(message).parse().unwrap_err();
+            assert!(e.contains(expected), "{}", e);
+        }
+    }
+
+    #[test]
+    fn poll_interval_bounds() {
+        let context = context();
+        for interval_ms in [249, 60_001, 0] {
+            let e = context.set_poll_interval(interval_ms).unwrap_err();
+            assert_eq!(e, "interval_ms must be between 250 and 60000");
+        }
+        assert_eq!(context.poll_interval_ms.load(Ordering::Relaxed), DEFAULT_POLL_INTERVAL_MS);
+
+        for interval_ms in [250, 60_000] {
+            context.set_poll_interval(interval_ms).unwrap();
+            assert_eq!(context.poll_interval_ms.load(Ordering::Relaxed), interval_ms);
+        }
+    }
+
+    #[test]
+    fn result_message_carries_the_error() {
+        let ok = Ok(());
+        assert_eq!(
+            serde_json::to_value(CommandResultMessage::new("1", &ok)).unwrap(),
+            json!({ "type": "command_result", "id": "1", "ok": true })
+        );
+        let rejected = Err("Reporting is paused".to_string());
+        assert_eq!(
+            serde_json::to_value(CommandResultMessage::new("2", &rejected)).unwrap(),
+            json!({ "type": "command_result", "id": "2", "ok": false, "error": "Reporting is paused" })
+        );
+    }
+}
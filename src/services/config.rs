@@ -6,6 +6,17 @@ use std::fs;
 use std::path::PathBuf;
 use tracing::info;
 
+use super::artwork::ArtworkCacheConfig;
+use super::auth::AuthMode;
+use super::codec::Codec;
+use super::deflate::CompressionConfig;
+use super::queue::QueueConfig;
+use super::reconnect::ReconnectPolicy;
+use super::reporter::{KeepaliveConfig, Transport};
+use super::tls::TlsConfig;
+use super::mix_space::MixSpaceConfig;
+use super::mqtt::MqttConfig;
+use super::webhook::WebhookConfig;
 use super::ReporterConfig;
 
 const CONFIG_FILE: &str = "config.toml";
@@ -29,7 +40,21 @@ impl Default for ReporterConfig {
             enabled: false,
             ws_url: String::new(),
             token: String::new(),
+            auth_mode: AuthMode::default(),
+            codec: Codec::default(),
             enable_media_reporting: false,
+            queue: QueueConfig::default(),
+            reconnect: ReconnectPolicy::default(),
+            keepalive: KeepaliveConfig::default(),
+            compression: CompressionConfig::default(),
+            artwork_cache: ArtworkCacheConfig::default(),
+            proxy_url: None,
+            tls: TlsConfig::default(),
+            transport: Transport::default(),
+            webhook: WebhookConfig::default(),
+            mqtt: MqttConfig::default(),
+            mix_space: MixSpaceConfig::default(),
+            endpoints: Vec::new(),
         }
     }
 }
@@ -91,8 +116,8 @@ pub fn save_config(config: &AppConfig) -> Result<(), String> {
     let content = toml::to_string_pretty(config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
+    // The content carries endpoint tokens, only the path is logged
     info!("Writing config to: {}", path.display());
-    info!("Config content:\n{}", content);
 
     fs::write(&path, content)
         .map_err(|e| format!("Failed to write config file: {}", e))?;
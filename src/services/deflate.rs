@@ -0,0 +1,874 @@
+//! permessage-deflate (RFC 7692) for the reporter WebSocket
+//! tungstenite has no extension support, so compression happens in a stream adapter between
+//! TLS and tungstenite: outgoing frames are compressed, incoming compressed messages are
+//! inflated into plain frames before tungstenite parses them.
+//!
+//! Only the client side is implemented. The window is always 32 KiB, so neither `*_max_window_bits`
+//! parameter is offered; a response restricting a window, or carrying anything else we didn't
+//! ask for, fails the connection as RFC 7692 requires.
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tracing::info;
+
+/// Extension token in `Sec-WebSocket-Extensions`
+const EXTENSION: &str = "permessage-deflate";
+
+/// Trailer removed from every compressed message (RFC 7692 §7.2.1)
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Upper bound on an inflated message, against decompression bombs
+const MAX_INFLATED: usize = 16 << 20;
+
+/// Outgoing bytes buffered before writes start waiting on the socket
+const MAX_PENDING: usize = 256 * 1024;
+
+/// Compression settings, under `[reporter.compression]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CompressionConfig {
+    /// Offer permessage-deflate when connecting
+    pub enabled: bool,
+    /// Keep our compression window between messages: better ratio, 32 KiB of state
+    pub client_context_takeover: bool,
+    /// Let the server keep its window between messages
+    pub server_context_takeover: bool,
+    /// Messages smaller than this go out uncompressed
+    pub min_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            client_context_takeover: true,
+            server_context_takeover: true,
+            min_size: 64,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// `Sec-WebSocket-Extensions` request header value, `None` when disabled
+    pub fn offer(&self) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        let mut offer = EXTENSION.to_string();
+        if !self.client_context_takeover {
+            offer.push_str("; client_no_context_takeover");
+        }
+        if !self.server_context_takeover {
+            offer.push_str("; server_no_context_takeover");
+        }
+        Some(offer)
+    }
+}
+
+/// Traffic counters of one endpoint, kept across reconnects
+#[derive(Debug, Default)]
+pub struct CompressionStats {
+    /// Whether the current connection negotiated permessage-deflate
+    pub active: AtomicBool,
+    /// Payload bytes before compression / after inflating
+    pub raw_bytes: AtomicU64,
+    /// Payload bytes actually on the wire for those messages
+    pub wire_bytes: AtomicU64,
+}
+
+impl CompressionStats {
+    pub fn bytes_saved(&self) -> u64 {
+        self.raw_bytes.load(Ordering::Relaxed).saturating_sub(self.wire_bytes.load(Ordering::Relaxed))
+    }
+
+    fn record(&self, raw: usize, wire: usize) {
+        self.raw_bytes.fetch_add(raw as u64, Ordering::Relaxed);
+        self.wire_bytes.fetch_add(wire as u64, Ordering::Relaxed);
+    }
+}
+
+/// Parameters the server accepted
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Negotiated {
+    client_no_context_takeover: bool,
+    server_no_context_takeover: bool,
+}
+
+/// Find permessage-deflate in the response head, an error when the server's parameters are unusable
+fn negotiate(head: &[u8]) -> Result<Option<Negotiated>, String> {
+    let head = String::from_utf8_lossy(head);
+    let values = head.lines().filter_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("sec-websocket-extensions").then(|| value.to_string())
+    });
+    for value in values {
+        for extension in value.split(',') {
+            let mut params = extension.split(';').map(str::trim);
+            if !params.next().is_some_and(|name| name.eq_ignore_ascii_case(EXTENSION)) {
+                continue;
+            }
+            let mut negotiated = Negotiated {
+                client_no_context_takeover: false,
+                server_no_context_takeover: false,
+            };
+            let mut seen = Vec::new();
+            for param in params {
+                let (key, value) = match param.split_once('=') {
+                    Some((key, value)) => (key.trim().to_ascii_lowercase(), Some(value.trim().trim_matches('"'))),
+                    None => (param.to_ascii_lowercase(), None),
+                };
+                if seen.contains(&key) {
+                    return Err(format!("Duplicate permessage-deflate parameter: {}", key));
+                }
+                match (key.as_str(), value) {
+                    ("client_no_context_takeover", None) => negotiated.client_no_context_takeover = true,
+                    ("server_no_context_takeover", None) => negotiated.server_no_context_takeover = true,
+                    // Not offered, but a full-size window is what both sides use anyway
+                    ("client_max_window_bits" | "server_max_window_bits", Some("15")) => {}
+                    _ => return Err(format!("Unexpected permessage-deflate parameter: {}", param)),
+                }
+                seen.push(key);
+            }
+            return Ok(Some(negotiated));
+        }
+    }
+    Ok(None)
+}
+
+/// Parsed frame header; `len` is the full frame size including the header
+struct FrameHeader {
+    first: u8,
+    mask: Option<[u8; 4]>,
+    payload_start: usize,
+    len: usize,
+}
+
+impl FrameHeader {
+    /// Header of the frame at the start of `buf`, `None` until the whole frame is buffered
+    fn parse(buf: &[u8]) -> Option<FrameHeader> {
+        if buf.len() < 2 {
+            return None;
+        }
+        let masked = buf[1] & 0x80 != 0;
+        let (payload_len, mut offset) = match buf[1] & 0x7f {
+            126 => (u16::from_be_bytes(buf.get(2..4)?.try_into().ok()?) as usize, 4),
+            127 => (u64::from_be_bytes(buf.get(2..10)?.try_into().ok()?) as usize, 10),
+            len => (len as usize, 2),
+        };
+        let mask = if masked {
+            let key = buf.get(offset..offset + 4)?.try_into().ok()?;
+            offset += 4;
+            Some(key)
+        } else {
+            None
+        };
+        let len = offset.checked_add(payload_len)?;
+        (buf.len() >= len).then_some(FrameHeader { first: buf[0], mask, payload_start: offset, len })
+    }
+
+    fn fin(&self) -> bool {
+        self.first & 0x80 != 0
+    }
+
+    fn rsv1(&self) -> bool {
+        self.first & 0x40 != 0
+    }
+
+    fn opcode(&self) -> u8 {
+        self.first & 0x0f
+    }
+
+    /// Text or binary frame starting a message
+    fn starts_data_message(&self) -> bool {
+        matches!(self.opcode(), 0x1 | 0x2)
+    }
+
+    fn is_control(&self) -> bool {
+        self.opcode() & 0x08 != 0
+    }
+
+    fn payload(&self, frame: &[u8]) -> Vec<u8> {
+        let mut payload = frame[self.payload_start..self.len].to_vec();
+        if let Some(mask) = self.mask {
+            apply_mask(&mut payload, mask);
+        }
+        payload
+    }
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[i & 3];
+    }
+}
+
+fn write_frame(out: &mut Vec<u8>, first: u8, payload: &[u8], mask: Option<[u8; 4]>) {
+    out.push(first);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => out.push(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            out.push(mask_bit | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(mask_bit | 127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            out.extend_from_slice(&mask);
+            let start = out.len();
+            out.extend_from_slice(payload);
+            apply_mask(&mut out[start..], mask);
+        }
+        None => out.extend_from_slice(payload),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Bytes of the HTTP head up to and including the blank line, once complete
+fn head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|window| window == b"\r\n\r\n").map(|pos| pos + 4)
+}
+
+enum Phase {
+    /// HTTP upgrade still in flight
+    Handshake,
+    /// Extension negotiated, frames are rewritten
+    Deflate(Negotiated),
+    /// No extension, bytes pass through untouched
+    Plain,
+}
+
+/// Stream adapter applying permessage-deflate below tungstenite
+pub struct DeflateStream<S> {
+    inner: S,
+    offered: bool,
+    min_size: usize,
+    stats: Arc<CompressionStats>,
+    /// Read side state
+    read_phase: Phase,
+    read_raw: Vec<u8>,
+    read_ready: Vec<u8>,
+    read_pos: usize,
+    /// Compressed message being reassembled: opcode and payload so far
+    inflating: Option<(u8, Vec<u8>)>,
+    inflater: Decompress,
+    /// Write side state
+    write_handshake_done: bool,
+    write_raw: Vec<u8>,
+    write_pending: Vec<u8>,
+    compressor: Compress,
+}
+
+impl<S> DeflateStream<S> {
+    /// `offered` must match whether the request carried the extension offer
+    pub fn new(inner: S, offered: bool, min_size: usize, stats: Arc<CompressionStats>) -> Self {
+        stats.active.store(false, Ordering::Relaxed);
+        Self {
+            inner,
+            offered,
+            min_size,
+            stats,
+            read_phase: if offered { Phase::Handshake } else { Phase::Plain },
+            read_raw: Vec::new(),
+            read_ready: Vec::new(),
+            read_pos: 0,
+            inflating: None,
+            inflater: Decompress::new(false),
+            write_handshake_done: !offered,
+            write_raw: Vec::new(),
+            write_pending: Vec::new(),
+            compressor: Compress::new(Compression::default(), false),
+        }
+    }
+
+    fn negotiated(&self) -> Option<Negotiated> {
+        match self.read_phase {
+            Phase::Deflate(negotiated) => Some(negotiated),
+            _ => None,
+        }
+    }
+
+    /// Move what arrived in `read_raw` to `read_ready`, inflating compressed messages
+    fn process_read(&mut self) -> io::Result<()> {
+        if let Phase::Handshake = self.read_phase {
+            let Some(end) = head_end(&self.read_raw) else { return Ok(()) };
+            let head: Vec<u8> = self.read_raw.drain(..end).collect();
+            self.read_phase = match negotiate(&head).map_err(invalid)? {
+                Some(negotiated) => {
+                    info!("permessage-deflate negotiated: {:?}", negotiated);
+                    self.stats.active.store(true, Ordering::Relaxed);
+                    Phase::Deflate(negotiated)
+                }
+                None => {
+                    info!("Server declined permessage-deflate");
+                    Phase::Plain
+                }
+            };
+            self.read_ready.extend_from_slice(&head);
+        }
+
+        let Some(negotiated) = self.negotiated() else {
+            self.read_ready.append(&mut self.read_raw);
+            return Ok(());
+        };
+
+        let mut consumed = 0;
+        while let Some(header) = FrameHeader::parse(&self.read_raw[consumed..]) {
+            let frame = &self.read_raw[consumed..consumed + header.len];
+            let continues_compressed = self.inflating.is_some() && header.opcode() == 0x0;
+
+            if header.rsv1() && !header.starts_data_message() {
+                // Continuations and control frames never carry RSV1 (RFC 7692 §6.1)
+                return Err(invalid("RSV1 set on a frame that can't be compressed"));
+            } else if self.inflating.is_some() && !header.is_control() && !continues_compressed {
+                // Only control frames may sit between fragments of a message
+                return Err(invalid("Data frame inside a fragmented compressed message"));
+            } else if header.rsv1() || continues_compressed {
+                let payload = header.payload(frame);
+                let (_, buffer) = self.inflating.get_or_insert_with(|| (header.opcode(), Vec::new()));
+                buffer.extend_from_slice(&payload);
+                if buffer.len() > MAX_INFLATED {
+                    return Err(invalid("Compressed message too large"));
+                }
+                if header.fin() {
+                    let (opcode, compressed) = self.inflating.take().unwrap_or_default();
+                    let (inflated, ended) = inflate(&mut self.inflater, &compressed)?;
+                    if negotiated.server_no_context_takeover || ended {
+                        self.inflater.reset(false);
+                    }
+                    self.stats.record(inflated.len(), compressed.len());
+                    write_frame(&mut self.read_ready, 0x80 | opcode, &inflated, None);
+                }
+            } else {
+                self.read_ready.extend_from_slice(frame);
+            }
+            consumed += header.len;
+        }
+        self.read_raw.drain(..consumed);
+        if self.read_raw.len() > MAX_INFLATED {
+            return Err(invalid("Frame too large"));
+        }
+        Ok(())
+    }
+
+    /// Move complete frames from `write_raw` to `write_pending`, compressing data messages
+    fn process_write(&mut self) -> io::Result<()> {
+        if !self.write_handshake_done {
+            let Some(end) = head_end(&self.write_raw) else { return Ok(()) };
+            self.write_pending.extend(self.write_raw.drain(..end));
+            self.write_handshake_done = true;
+        }
+
+        // Until the response arrives nothing but the request is written
+        let negotiated = match &self.read_phase {
+            Phase::Deflate(negotiated) => *negotiated,
+            Phase::Handshake if self.offered => return Ok(()),
+            _ => {
+                self.write_pending.append(&mut self.write_raw);
+                return Ok(());
+            }
+        };
+
+        let mut consumed = 0;
+        while let Some(header) = FrameHeader::parse(&self.write_raw[consumed..]) {
+            let frame = &self.write_raw[consumed..consumed + header.len];
+            let payload_len = header.len - header.payload_start;
+            // Fragmented messages are rare here and are allowed to stay uncompressed
+            if header.starts_data_message() && header.fin() && !header.rsv1() && payload_len >= self.min_size {
+                let payload = header.payload(frame);
+                let compressed = deflate(&mut self.compressor, &payload)?;
+                if negotiated.client_no_context_takeover {
+                    self.compressor.reset();
+                }
+                self.stats.record(payload.len(), compressed.len());
+                // RFC 6455 §5.3: masks must be unpredictable, so they come from a CSPRNG
+                let mask: [u8; 4] = rand::random();
+                write_frame(&mut self.write_pending, header.first | 0x40, &compressed, Some(mask));
+            } else {
+                self.write_pending.extend_from_slice(frame);
+            }
+            consumed += header.len;
+        }
+        self.write_raw.drain(..consumed);
+        Ok(())
+    }
+}
+
+fn deflate(compressor: &mut Compress, input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 64);
+    let start_in = compressor.total_in();
+    loop {
+        let read = (compressor.total_in() - start_in) as usize;
+        if output.len() == output.capacity() {
+            output.reserve(output.capacity().max(64));
+        }
+        compressor
+            .compress_vec(&input[read..], &mut output, FlushCompress::Sync)
+            .map_err(|e| invalid(format!("Compression failed: {}", e)))?;
+        let done = (compressor.total_in() - start_in) as usize == input.len();
+        if done && output.len() < output.capacity() {
+            break;
+        }
+    }
+    if output.ends_with(&TRAILER) {
+        output.truncate(output.len() - TRAILER.len());
+    }
+    Ok(output)
+}
+
+/// Inflated message, and whether the sender ended the deflate stream (final block)
+fn inflate(inflater: &mut Decompress, compressed: &[u8]) -> io::Result<(Vec<u8>, bool)> {
+    let mut input = Vec::with_capacity(compressed.len() + TRAILER.len());
+    input.extend_from_slice(compressed);
+    input.extend_from_slice(&TRAILER);
+
+    let mut output = Vec::with_capacity(compressed.len() * 4 + 64);
+    let start_in = inflater.total_in();
+    loop {
+        let read = (inflater.total_in() - start_in) as usize;
+        if output.len() == output.capacity() {
+            if output.len() >= MAX_INFLATED {
+                return Err(invalid("Inflated message too large"));
+            }
+            output.reserve(output.capacity());
+        }
+        let status = inflater
+            .decompress_vec(&input[read..], &mut output, FlushDecompress::Sync)
+            .map_err(|e| invalid(format!("Invalid compressed message: {}", e)))?;
+        let done = (inflater.total_in() - start_in) as usize == input.len();
+        if status == Status::StreamEnd {
+            return Ok((output, true));
+        }
+        if done && output.len() < output.capacity() {
+            return Ok((output, false));
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// Write out `write_pending`
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_pending.is_empty() {
+            let written = match Pin::new(&mut self.inner).poll_write(cx, &self.write_pending) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(written)) => written,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+            self.write_pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.read_pos < this.read_ready.len() {
+                let available = &this.read_ready[this.read_pos..];
+                let n = available.len().min(buf.remaining());
+                buf.put_slice(&available[..n]);
+                this.read_pos += n;
+                if this.read_pos == this.read_ready.len() {
+                    this.read_ready.clear();
+                    this.read_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf) {
+                Poll::Ready(Ok(())) if chunk_buf.filled().is_empty() => return Poll::Ready(Ok(())),
+                Poll::Ready(Ok(())) => {
+                    this.read_raw.extend_from_slice(chunk_buf.filled());
+                    this.process_read()?;
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.write_pending.len() >= MAX_PENDING {
+            match this.poll_drain(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        this.write_raw.extend_from_slice(buf);
+        this.process_write()?;
+        // Best effort; whatever is left goes out on flush
+        if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.process_write()?;
+        match this.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const REQUEST: &[u8] = b"GET /ws HTTP/1.1\r\nHost: localhost\r\nSec-WebSocket-Extensions: permessage-deflate\r\n\r\n";
+
+    fn response(extensions: &str) -> Vec<u8> {
+        format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nSec-WebSocket-Extensions: {}\r\n\r\n", extensions)
+            .into_bytes()
+    }
+
+    /// Client stream past the handshake, the server having answered with `extensions`
+    fn connected(extensions: &str, min_size: usize) -> DeflateStream<()> {
+        let mut stream = DeflateStream::new((), true, min_size, Arc::default());
+        stream.write_raw.extend_from_slice(REQUEST);
+        stream.process_write().unwrap();
+        stream.read_raw.extend(response(extensions));
+        stream.process_read().unwrap();
+        assert!(stream.negotiated().is_some());
+        stream.write_pending.clear();
+        stream.read_ready.clear();
+        stream
+    }
+
+    /// What goes on the wire for frames written by tungstenite
+    fn send(stream: &mut DeflateStream<()>, frames: &[u8]) -> Vec<u8> {
+        stream.write_raw.extend_from_slice(frames);
+        stream.process_write().unwrap();
+        std::mem::take(&mut stream.write_pending)
+    }
+
+    /// What tungstenite reads for bytes arriving from the server
+    fn receive(stream: &mut DeflateStream<()>, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        stream.read_raw.extend_from_slice(bytes);
+        stream.process_read()?;
+        Ok(std::mem::take(&mut stream.read_ready))
+    }
+
+    /// Frame as tungstenite writes it, masked
+    fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_frame(&mut out, first, payload, Some([1, 2, 3, 4]));
+        out
+    }
+
+    fn server_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_frame(&mut out, first, payload, None);
+        out
+    }
+
+    /// Split a buffer into its frames: (first byte, unmasked payload)
+    fn frames(mut bytes: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        let mut frames = Vec::new();
+        while let Some(header) = FrameHeader::parse(bytes) {
+            frames.push((header.first, header.payload(bytes)));
+            bytes = &bytes[header.len..];
+        }
+        assert!(bytes.is_empty(), "trailing partial frame");
+        frames
+    }
+
+    /// Complete frames after the response head
+    fn frames_complete(bytes: &[u8]) -> usize {
+        let Some(mut rest) = head_end(bytes).map(|end| &bytes[end..]) else { return 0 };
+        let mut count = 0;
+        while let Some(header) = FrameHeader::parse(rest) {
+            rest = &rest[header.len..];
+            count += 1;
+        }
+        count
+    }
+
+    fn message(n: usize) -> Vec<u8> {
+        format!(r#"{{"type":"window_info","data":{{"title":"README.md - Code","n":{}}}}}"#, n).repeat(4).into_bytes()
+    }
+
+    #[test]
+    fn offer_lists_the_disabled_takeovers() {
+        assert_eq!(CompressionConfig::default().offer(), None);
+        let config = CompressionConfig { enabled: true, ..CompressionConfig::default() };
+        assert_eq!(config.offer().as_deref(), Some("permessage-deflate"));
+        let config = CompressionConfig { enabled: true, client_context_takeover: false, server_context_takeover: false, ..config };
+        assert_eq!(
+            config.offer().as_deref(),
+            Some("permessage-deflate; client_no_context_takeover; server_no_context_takeover")
+        );
+    }
+
+    #[test]
+    fn negotiate_reads_the_accepted_parameters() {
+        let head = response("x-other, permessage-deflate; client_no_context_takeover; Server_No_Context_Takeover");
+        let negotiated = negotiate(&head).unwrap().unwrap();
+        assert!(negotiated.client_no_context_takeover && negotiated.server_no_context_takeover);
+
+        let plain = negotiate(&response("permessage-deflate")).unwrap().unwrap();
+        assert!(!plain.client_no_context_takeover && !plain.server_no_context_takeover);
+        assert_eq!(negotiate(&response("permessage-deflate; server_max_window_bits=15")).unwrap(), Some(plain));
+        assert_eq!(negotiate(&response("x-other")).unwrap(), None);
+        assert_eq!(negotiate(b"HTTP/1.1 101 Switching Protocols\r\n\r\n").unwrap(), None);
+    }
+
+    #[test]
+    fn negotiate_rejects_unrequested_parameters() {
+        for extensions in [
+            "permessage-deflate; server_max_window_bits=10",
+            "permessage-deflate; server_max_window_bits=\"8\"",
+            "permessage-deflate; client_max_window_bits=9",
+            "permessage-deflate; client_max_window_bits",
+            "permessage-deflate; x_custom=1",
+            "permessage-deflate; client_no_context_takeover=1",
+            "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+        ] {
+            assert!(negotiate(&response(extensions)).is_err(), "accepted {}", extensions);
+        }
+    }
+
+    #[test]
+    fn rejected_negotiation_fails_the_connection() {
+        let mut stream = DeflateStream::new((), true, 0, Arc::default());
+        stream.read_raw.extend(response("permessage-deflate; server_max_window_bits=9"));
+        assert_eq!(stream.process_read().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(!stream.stats.active.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn declined_offer_passes_frames_through() {
+        let mut stream = DeflateStream::new((), true, 0, Arc::default());
+        stream.write_raw.extend_from_slice(REQUEST);
+        stream.process_write().unwrap();
+        stream.read_raw.extend_from_slice(b"HTTP/1.1 101 Switching Protocols\r\n\r\n");
+        stream.process_read().unwrap();
+        stream.write_pending.clear();
+        stream.read_ready.clear();
+
+        let frame = client_frame(0x81, &message(1));
+        assert_eq!(send(&mut stream, &frame), frame);
+        let frame = server_frame(0x81, &message(2));
+        assert_eq!(receive(&mut stream, &frame).unwrap(), frame);
+    }
+
+    #[test]
+    fn frames_wait_for_the_response() {
+        let mut stream = DeflateStream::new((), true, 0, Arc::default());
+        let frame = client_frame(0x81, &message(1));
+        stream.write_raw.extend_from_slice(REQUEST);
+        stream.write_raw.extend_from_slice(&frame);
+        stream.process_write().unwrap();
+        assert_eq!(stream.write_pending, REQUEST);
+
+        stream.read_raw.extend(response("permessage-deflate"));
+        stream.process_read().unwrap();
+        stream.process_write().unwrap();
+        let sent = frames(&stream.write_pending[REQUEST.len()..]);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, 0xc1);
+    }
+
+    /// Compress with context takeover or not, inflate like a server would
+    fn round_trip(no_context_takeover: bool) {
+        let extensions = if no_context_takeover { "permessage-deflate; client_no_context_takeover" } else { "permessage-deflate" };
+        let mut stream = connected(extensions, 0);
+        let mut server = Decompress::new(false);
+        let mut sizes = Vec::new();
+        for _ in 0..2 {
+            let wire = send(&mut stream, &client_frame(0x81, &message(1)));
+            let sent = frames(&wire);
+            assert_eq!(sent.len(), 1);
+            let (first, payload) = &sent[0];
+            assert_eq!(*first, 0xc1, "FIN, RSV1, text");
+            if no_context_takeover {
+                // Every message stands alone
+                server = Decompress::new(false);
+            }
+            assert_eq!(inflate(&mut server, payload).unwrap().0, message(1));
+            sizes.push(payload.len());
+        }
+        if no_context_takeover {
+            assert_eq!(sizes[0], sizes[1]);
+        } else {
+            assert!(sizes[1] < sizes[0], "second message refers back to the first: {:?}", sizes);
+        }
+        assert!(stream.stats.bytes_saved() > 0);
+    }
+
+    #[test]
+    fn compress_round_trip_with_context_takeover() {
+        round_trip(false);
+    }
+
+    #[test]
+    fn compress_round_trip_without_context_takeover() {
+        round_trip(true);
+    }
+
+    #[test]
+    fn small_and_fragmented_outgoing_messages_stay_plain() {
+        let mut stream = connected("permessage-deflate", 64);
+        let small = client_frame(0x81, b"{}");
+        assert_eq!(send(&mut stream, &small), small);
+
+        let mut fragmented = client_frame(0x01, &message(1));
+        fragmented.extend(client_frame(0x89, b"ping"));
+        fragmented.extend(client_frame(0x80, &message(2)));
+        assert_eq!(send(&mut stream, &fragmented), fragmented);
+
+        let control = client_frame(0x8a, &[0; 100]);
+        assert_eq!(send(&mut stream, &control), control);
+    }
+
+    #[test]
+    fn inflates_messages_from_the_server() {
+        let mut stream = connected("permessage-deflate", 0);
+        let mut server = Compress::new(Compression::default(), false);
+        for n in 0..3 {
+            let compressed = deflate(&mut server, &message(n)).unwrap();
+            let received = receive(&mut stream, &server_frame(0xc1, &compressed)).unwrap();
+            assert_eq!(frames(&received), [(0x81, message(n))]);
+        }
+    }
+
+    #[test]
+    fn server_no_context_takeover_resets_the_inflater() {
+        let mut stream = connected("permessage-deflate; server_no_context_takeover", 0);
+        for n in 0..2 {
+            let mut server = Compress::new(Compression::default(), false);
+            let compressed = deflate(&mut server, &message(n)).unwrap();
+            let received = receive(&mut stream, &server_frame(0xc2, &compressed)).unwrap();
+            assert_eq!(frames(&received), [(0x82, message(n))]);
+        }
+    }
+
+    #[test]
+    fn fragmented_message_with_control_frame_in_between() {
+        let mut stream = connected("permessage-deflate", 0);
+        let mut server = Compress::new(Compression::default(), false);
+        let compressed = deflate(&mut server, &message(7)).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+
+        // RSV1 only on the first frame
+        let mut bytes = server_frame(0x41, head);
+        bytes.extend(server_frame(0x89, b"ping"));
+        bytes.extend(server_frame(0x80, tail));
+        let received = receive(&mut stream, &bytes).unwrap();
+        assert_eq!(frames(&received), [(0x89, b"ping".to_vec()), (0x81, message(7))]);
+    }
+
+    #[test]
+    fn frames_split_across_reads() {
+        let mut stream = connected("permessage-deflate", 0);
+        let mut server = Compress::new(Compression::default(), false);
+        let mut bytes = server_frame(0xc1, &deflate(&mut server, &message(1)).unwrap());
+        bytes.extend(server_frame(0x8a, b"pong"));
+        bytes.extend(server_frame(0xc1, &deflate(&mut server, &message(2)).unwrap()));
+
+        let mut received = Vec::new();
+        for byte in &bytes {
+            received.extend(receive(&mut stream, std::slice::from_ref(byte)).unwrap());
+        }
+        assert_eq!(frames(&received), [(0x81, message(1)), (0x8a, b"pong".to_vec()), (0x81, message(2))]);
+    }
+
+    #[test]
+    fn rejects_misplaced_rsv1() {
+        let mut server = Compress::new(Compression::default(), false);
+        let compressed = deflate(&mut server, &message(1)).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+
+        let mut stream = connected("permessage-deflate", 0);
+        let mut bytes = server_frame(0x41, head);
+        bytes.extend(server_frame(0xc0, tail));
+        assert!(receive(&mut stream, &bytes).is_err(), "RSV1 on a continuation");
+
+        let mut stream = connected("permessage-deflate", 0);
+        assert!(receive(&mut stream, &server_frame(0xc9, b"ping")).is_err(), "RSV1 on a control frame");
+
+        let mut stream = connected("permessage-deflate", 0);
+        let mut bytes = server_frame(0x41, head);
+        bytes.extend(server_frame(0x81, b"{}"));
+        assert!(receive(&mut stream, &bytes).is_err(), "new message inside a fragmented one");
+    }
+
+    #[tokio::test]
+    async fn round_trip_through_the_stream() {
+        // A tiny pipe so frames arrive in pieces
+        let (client, mut server) = tokio::io::duplex(7);
+        let stats = Arc::new(CompressionStats::default());
+        let mut client = DeflateStream::new(client, true, 0, stats.clone());
+
+        let server_side = tokio::spawn(async move {
+            let mut head = Vec::new();
+            while head_end(&head).is_none() {
+                head.push(server.read_u8().await.unwrap());
+            }
+            server.write_all(&response("permessage-deflate")).await.unwrap();
+
+            let mut compressor = Compress::new(Compression::default(), false);
+            let reply = deflate(&mut compressor, &message(2)).unwrap();
+            server.write_all(&server_frame(0xc1, &reply)).await.unwrap();
+
+            let mut raw = Vec::new();
+            loop {
+                raw.push(server.read_u8().await.unwrap());
+                if FrameHeader::parse(&raw).is_some() {
+                    return frames(&raw);
+                }
+            }
+        });
+
+        client.write_all(REQUEST).await.unwrap();
+        client.flush().await.unwrap();
+        let mut received = Vec::new();
+        while frames_complete(&received) < 1 {
+            let mut chunk = [0u8; 5];
+            let n = client.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "closed early");
+            received.extend_from_slice(&chunk[..n]);
+        }
+        let end = head_end(&received).unwrap();
+        assert_eq!(frames(&received[end..]), [(0x81, message(2))]);
+        assert!(stats.active.load(Ordering::Relaxed));
+
+        client.write_all(&client_frame(0x81, &message(1))).await.unwrap();
+        client.flush().await.unwrap();
+        let sent = server_side.await.unwrap();
+        assert_eq!(sent[0].0, 0xc1);
+        assert_eq!(inflate(&mut Decompress::new(false), &sent[0].1).unwrap().0, message(1));
+    }
+}
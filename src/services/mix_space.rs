@@ -0,0 +1,381 @@
+//! Mix Space process reporter sink
+//! Drop-in replacement for Kizuna: POSTs `{timestamp, process, media, key}` to the
+//! Mix Space `ps/update` function, e.g. `https://api.example.com/api/v2/fn/ps/update`
+//!
+//! The endpoint `token` is sent as `key`. Only the current state is reported: changes are
+//! merged until `min_interval_ms` has passed, nothing is queued while the server is down.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{error, info};
+use url::Url;
+
+use crate::platform::unix_millis;
+use super::artwork::ArtworkUrls;
+use super::auth;
+use super::reporter::{ConnectionStatus, EndpointConfig, ReporterConfig, ReporterMessage, SessionState};
+use super::webhook::{describe_error, http_client};
+
+/// Mix Space settings, under `[reporter.mix_space]` or `[reporter.endpoints.mix_space]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MixSpaceConfig {
+    /// Minimum time between two reports
+    pub min_interval_ms: u64,
+    /// Resend unchanged state this often so it doesn't go stale on the server, 0 disables
+    pub heartbeat_secs: u64,
+    /// Timeout of a single request
+    pub timeout_ms: u64,
+}
+
+impl Default for MixSpaceConfig {
+    fn default() -> Self {
+        Self {
+            min_interval_ms: 10_000,
+            heartbeat_secs: 60,
+            timeout_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Media {
+    title: String,
+    artist: String,
+}
+
+/// What Mix Space shows: the foreground process and what's playing
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Presence {
+    process: Option<String>,
+    media: Option<Media>,
+}
+
+impl Presence {
+    fn apply(&mut self, msg: &ReporterMessage) {
+        let Some(entry) = msg.to_queued() else { return };
+        let payload = &entry.payload;
+        match entry.kind.as_str() {
+            "window_info" => {
+                self.process = payload["data"]["process_name"].as_str().map(str::to_string);
+            }
+            "media_playback" => {
+                let playing = payload["playback_state"]["playing"].as_bool().unwrap_or(false);
+                let metadata = &payload["metadata"];
+                self.media = match metadata["title"].as_str() {
+                    Some(title) if playing => Some(Media {
+                        title: title.to_string(),
+                        artist: metadata["artist"].as_str().unwrap_or_default().to_string(),
+                    }),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Request body of `ps/update`
+#[derive(Serialize)]
+struct ProcessReport<'a> {
+    /// Unix seconds
+    timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media: Option<&'a Media>,
+    key: &'a str,
+}
+
+/// Post `presence` once; the error carries the server's `Retry-After` when rate limited
+async fn report(
+    client: &reqwest::Client,
+    endpoint: &EndpointConfig,
+    presence: &Presence,
+) -> Result<(), (String, Option<Duration>)> {
+    let url = Url::parse(&endpoint.ws_url).map_err(|e| (format!("Invalid URL: {}", e), None))?;
+    let body = ProcessReport {
+        timestamp: unix_millis() / 1000,
+        process: presence.process.as_deref(),
+        media: presence.media.as_ref(),
+        key: &endpoint.token,
+    };
+
+    let response = client
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| (auth::redact_token(&describe_error(e), &endpoint.token), None))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    Err((format!("HTTP {}", status), retry_after))
+}
+
+/// When the next report is due; nothing is sent before the first window or track
+fn next_due(
+    settings: &MixSpaceConfig,
+    presence: &Presence,
+    reported: Option<&Presence>,
+    last_report: Option<Instant>,
+    retry_at: Option<Instant>,
+) -> Option<Instant> {
+    if reported.is_none() && *presence == Presence::default() {
+        return None;
+    }
+    let earliest = match last_report {
+        None => Instant::now(),
+        Some(last) if reported != Some(presence) => last + Duration::from_millis(settings.min_interval_ms),
+        Some(last) if settings.heartbeat_secs > 0 => last + Duration::from_secs(settings.heartbeat_secs),
+        Some(_) => return None,
+    };
+    Some(retry_at.map_or(earliest, |retry| retry.max(earliest)))
+}
+
+/// Connection task of a Mix Space endpoint, the counterpart of `Reporter::run_reporter`
+pub(super) async fn run_mix_space(
+    config: Arc<RwLock<ReporterConfig>>,
+    index: usize,
+    mut rx: mpsc::UnboundedReceiver<ReporterMessage>,
+    _artwork_urls: Arc<RwLock<ArtworkUrls>>,
+    _session: Arc<Mutex<SessionState>>,
+    status: Arc<ConnectionStatus>,
+) {
+    let mut presence = Presence::default();
+    let mut reported: Option<Presence> = None;
+    let mut last_report: Option<Instant> = None;
+    let mut retry_at: Option<Instant> = None;
+    let mut failures: u32 = 0;
+    // Client is rebuilt only when the endpoint settings change
+    let mut client: Option<(String, reqwest::Client)> = None;
+
+    loop {
+        let cfg = config.read().unwrap().clone();
+        let endpoint = cfg.endpoint(index).filter(|endpoint| endpoint.enabled);
+        if endpoint.is_none() {
+            status.set_connected(false);
+        }
+
+        let due = endpoint
+            .as_ref()
+            .and_then(|endpoint| next_due(&endpoint.mix_space, &presence, reported.as_ref(), last_report, retry_at));
+
+        // Without anything due, wake up now and then to pick up config changes
+        tokio::select! {
+            msg = rx.recv() => {
+                let Some(msg) = msg else { return };
+                let allowed = cfg.endpoint(index).is_none_or(|endpoint| endpoint.filter.allows(msg.kind()));
+                if allowed {
+                    presence.apply(&msg);
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(due.unwrap_or_else(|| Instant::now() + Duration::from_secs(5))) => {
+                if due.is_none() {
+                    continue;
+                }
+            }
+        }
+        let Some(endpoint) = endpoint else { continue };
+
+        let key = serde_json::to_string(&endpoint).unwrap_or_default();
+        if client.as_ref().is_none_or(|(current, _)| *current != key) {
+            let built = Url::parse(&endpoint.ws_url)
+                .map_err(|e| format!("Invalid URL: {}", e))
+                .and_then(|url| http_client(&endpoint, &url, Duration::from_millis(endpoint.mix_space.timeout_ms)));
+            match built {
+                Ok(built) => {
+                    info!("[{}] Reporting to Mix Space at {}", endpoint.name, auth::redact_url_str(&endpoint.ws_url));
+                    client = Some((key, built));
+                }
+                Err(e) => {
+                    error!("[{}] {}", endpoint.name, e);
+                    client = None;
+                    retry_at = Some(Instant::now() + Duration::from_secs(5));
+                    continue;
+                }
+            }
+        }
+        let Some((_, http)) = client.as_ref() else { continue };
+
+        let started = Instant::now();
+        match report(http, &endpoint, &presence).await {
+            Ok(()) => {
+                if failures > 0 {
+                    info!("[{}] ✅ Mix Space reachable again", endpoint.name);
+                }
+                failures = 0;
+                retry_at = None;
+                reported = Some(presence.clone());
+                last_report = Some(Instant::now());
+                status.set_connected(true);
+                status.latency_ms.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+                if let Ok(mut reconnect) = status.reconnect.write() {
+                    reconnect.attempt = 0;
+                    reconnect.next_retry_at = None;
+                }
+            }
+            Err((e, retry_after)) => {
+                failures = failures.saturating_add(1);
+                // Honor the server's rate limit over our own backoff
+                let delay = retry_after.unwrap_or_else(|| cfg.reconnect.delay(failures));
+                error!("[{}] ❌ Mix Space report failed: {}, retrying in {:.1}s", endpoint.name, e, delay.as_secs_f64());
+                status.set_connected(false);
+                retry_at = Some(Instant::now() + delay);
+                if let Ok(mut reconnect) = status.reconnect.write() {
+                    reconnect.attempt = failures;
+                    reconnect.next_retry_at = Some(unix_millis() + delay.as_millis() as u64);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{MediaMetadata, PlaybackState, WindowInfo};
+    use crate::services::reporter::{MediaPlaybackMessage, Transport, WindowInfoMessage};
+    use crate::services::test_http::{serve, Reply};
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    fn window(process_name: &str) -> ReporterMessage {
+        ReporterMessage::WindowInfo(WindowInfoMessage::new(&WindowInfo {
+            title: "README.md - Code".to_string(),
+            icon_data: None,
+            process_name: process_name.to_string(),
+            pid: 100,
+            app_id: None,
+        }))
+    }
+
+    fn track(playing: bool) -> ReporterMessage {
+        let metadata = MediaMetadata {
+            title: Some("Song".to_string()),
+            artist: Some("Artist".to_string()),
+            ..MediaMetadata::default()
+        };
+        ReporterMessage::MediaPlayback(MediaPlaybackMessage::new(&metadata, &PlaybackState::new(playing, 1.0, 0.0)))
+    }
+
+    fn endpoint(addr: std::net::SocketAddr) -> EndpointConfig {
+        let config = ReporterConfig {
+            enabled: true,
+            ws_url: format!("http://{}/api/v2/fn/ps/update", addr),
+            token: "mix-key".to_string(),
+            proxy_url: Some("direct".to_string()),
+            transport: Transport::MixSpace,
+            ..ReporterConfig::default()
+        };
+        config.endpoint(0).unwrap()
+    }
+
+    async fn post(presence: &Presence, reply: impl Into<Reply>) -> (Result<(), (String, Option<Duration>)>, Value) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = endpoint(listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(listener, vec![reply.into()]));
+        let client = http_client(&endpoint, &Url::parse(&endpoint.ws_url).unwrap(), Duration::from_secs(5)).unwrap();
+
+        let result = report(&client, &endpoint, presence).await;
+        let request = &server.await.unwrap()[0];
+        assert_eq!(request.path, "/api/v2/fn/ps/update");
+        (result, serde_json::from_slice(&request.body).unwrap())
+    }
+
+    #[test]
+    fn presence_follows_window_and_playing_media() {
+        let mut presence = Presence::default();
+        presence.apply(&window("code"));
+        assert_eq!(presence.process.as_deref(), Some("code"));
+        assert_eq!(presence.media, None);
+
+        presence.apply(&track(true));
+        assert_eq!(presence.media, Some(Media { title: "Song".to_string(), artist: "Artist".to_string() }));
+        assert_eq!(presence.process.as_deref(), Some("code"));
+
+        // Paused media isn't shown
+        presence.apply(&track(false));
+        assert_eq!(presence.media, None);
+        assert_eq!(presence.process.as_deref(), Some("code"));
+    }
+
+    #[tokio::test]
+    async fn report_posts_process_media_and_key() {
+        let presence = Presence {
+            process: Some("code".to_string()),
+            media: Some(Media { title: "Song".to_string(), artist: "Artist".to_string() }),
+        };
+        let (result, body) = post(&presence, 200).await;
+        assert!(result.is_ok());
+
+        assert_eq!(body["process"], "code");
+        assert_eq!(body["media"], serde_json::json!({ "title": "Song", "artist": "Artist" }));
+        assert_eq!(body["key"], "mix-key");
+        // Seconds, not milliseconds
+        let now = unix_millis() / 1000;
+        let timestamp = body["timestamp"].as_u64().unwrap();
+        assert!(timestamp.abs_diff(now) <= 5, "{} vs {}", timestamp, now);
+    }
+
+    #[tokio::test]
+    async fn report_leaves_out_missing_media() {
+        let presence = Presence { process: Some("code".to_string()), media: None };
+        let (result, body) = post(&presence, 200).await;
+        assert!(result.is_ok());
+        assert!(body.get("media").is_none(), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_carries_retry_after() {
+        let presence = Presence { process: Some("code".to_string()), media: None };
+        let reply = Reply { status: 429, headers: vec![("Retry-After", "7".to_string())] };
+        let (result, _) = post(&presence, reply).await;
+        assert_eq!(result.unwrap_err().1, Some(Duration::from_secs(7)));
+
+        let (result, _) = post(&presence, 500).await;
+        assert_eq!(result.unwrap_err().1, None);
+    }
+
+    #[test]
+    fn reports_are_spaced_by_the_minimum_interval() {
+        let settings = MixSpaceConfig::default();
+        let idle = Presence::default();
+        let coding = Presence { process: Some("code".to_string()), media: None };
+        let browsing = Presence { process: Some("firefox".to_string()), media: None };
+
+        // Nothing to report yet, then the first state goes out right away
+        assert_eq!(next_due(&settings, &idle, None, None, None), None);
+        let first = next_due(&settings, &coding, None, None, None).unwrap();
+        assert!(first <= Instant::now());
+
+        // A change waits out the 10s minimum, unchanged state the heartbeat
+        let last = Instant::now();
+        let change = next_due(&settings, &browsing, Some(&coding), Some(last), None);
+        assert_eq!(change, Some(last + Duration::from_secs(10)));
+        let heartbeat = next_due(&settings, &coding, Some(&coding), Some(last), None);
+        assert_eq!(heartbeat, Some(last + Duration::from_secs(60)));
+        let quiet = MixSpaceConfig { heartbeat_secs: 0, ..MixSpaceConfig::default() };
+        assert_eq!(next_due(&quiet, &coding, Some(&coding), Some(last), None), None);
+
+        // Retry-After pushes the report further out, never earlier
+        let retry = last + Duration::from_secs(30);
+        assert_eq!(next_due(&settings, &browsing, Some(&coding), Some(last), Some(retry)), Some(retry));
+        let early_retry = last + Duration::from_secs(1);
+        let change = next_due(&settings, &browsing, Some(&coding), Some(last), Some(early_retry));
+        assert_eq!(change, Some(last + Duration::from_secs(10)));
+    }
+}
@@ -1,8 +1,25 @@
 //! 业务服务层
 //! 包含数据上报、状态管理等业务逻辑
 
+pub mod artwork;
+pub mod auth;
+pub mod codec;
+pub mod command;
 pub mod config;
+pub mod deflate;
+pub mod mix_space;
+pub mod mqtt;
+pub mod protocol;
+pub mod proxy;
+pub mod queue;
+pub mod reconnect;
 pub mod reporter;
+pub mod tls;
+pub mod webhook;
+
+#[cfg(test)]
+#[path = "../../tests/common/http.rs"]
+mod test_http;
 
 #[allow(unused_imports)]
 pub use config::{load_config, save_reporter_config, get_log_level};
@@ -0,0 +1,578 @@
+//! MQTT transport
+//! Publishes window and media state as retained messages, e.g. for Home Assistant
+//!
+//! Topics under `topic_prefix` (default `shikenmatrix`):
+//! - `<prefix>/window`: latest `window_info` payload
+//! - `<prefix>/media`: latest `media_playback` payload
+//! - `<prefix>/status`: `online`, or `offline` via the last will
+//!
+//! Artwork isn't published. The endpoint `token` is the password, `mqtt.username` the user name.
+
+use rumqttc::v5;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::platform::unix_millis;
+use super::artwork::ArtworkUrls;
+use super::auth;
+use super::queue::{OfflineQueue, QueuedMessage};
+use super::reporter::{ConnectionStatus, EndpointConfig, Reporter, ReporterConfig, ReporterMessage, SessionState};
+use super::tls;
+
+const STATUS_ONLINE: &str = "online";
+const STATUS_OFFLINE: &str = "offline";
+
+/// Protocol version spoken to the broker
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MqttVersion {
+    #[default]
+    #[serde(rename = "3.1.1")]
+    V311,
+    #[serde(rename = "5")]
+    V5,
+}
+
+/// MQTT settings, under `[reporter.mqtt]` or `[reporter.endpoints.mqtt]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub version: MqttVersion,
+    /// Client identifier, empty picks a random one per run
+    pub client_id: String,
+    /// User name sent with the endpoint `token` as password
+    pub username: String,
+    /// Prefix of the state and status topics
+    pub topic_prefix: String,
+    /// 0, 1 or 2
+    pub qos: u8,
+    /// Publish state as retained messages
+    pub retain: bool,
+    pub keep_alive_secs: u64,
+    /// Publish Home Assistant MQTT discovery payloads on connect
+    pub discovery: bool,
+    /// Home Assistant discovery prefix
+    pub discovery_prefix: String,
+    /// Device id used in discovery topics and unique ids
+    pub node_id: String,
+    /// Device name shown in Home Assistant
+    pub device_name: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            version: MqttVersion::V311,
+            client_id: String::new(),
+            username: String::new(),
+            topic_prefix: "shikenmatrix".to_string(),
+            qos: 1,
+            retain: true,
+            keep_alive_secs: 30,
+            discovery: false,
+            discovery_prefix: "homeassistant".to_string(),
+            node_id: "shikenmatrix".to_string(),
+            device_name: "ShikenMatrix".to_string(),
+        }
+    }
+}
+
+struct Topics {
+    window: String,
+    media: String,
+    status: String,
+}
+
+impl Topics {
+    fn new(config: &MqttConfig) -> Self {
+        let prefix = config.topic_prefix.trim_end_matches('/');
+        Self {
+            window: format!("{}/window", prefix),
+            media: format!("{}/media", prefix),
+            status: format!("{}/status", prefix),
+        }
+    }
+
+    fn for_kind(&self, kind: &str) -> Option<&str> {
+        match kind {
+            "window_info" => Some(&self.window),
+            "media_playback" => Some(&self.media),
+            _ => None,
+        }
+    }
+}
+
+/// Client half, for either protocol version
+enum Client {
+    V4(rumqttc::AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+impl Client {
+    /// Hand a message to the event loop without waiting
+    fn publish(&self, topic: &str, qos: u8, retain: bool, payload: impl Into<Vec<u8>>) -> Result<(), String> {
+        let payload = payload.into();
+        let result = match self {
+            Client::V4(client) => client.try_publish(topic, qos_v4(qos), retain, payload).map_err(|e| e.to_string()),
+            Client::V5(client) => client.try_publish(topic, qos_v5(qos), retain, payload).map_err(|e| e.to_string()),
+        };
+        result.map_err(|e| format!("Failed to publish to {}: {}", topic, e))
+    }
+}
+
+/// Event loop half, for either protocol version
+enum Events {
+    V4(Box<rumqttc::EventLoop>),
+    V5(Box<v5::EventLoop>),
+}
+
+/// Events the transport cares about
+enum Polled {
+    Connected,
+    PingSent,
+    PingAnswered,
+    Other,
+}
+
+impl Events {
+    async fn poll(&mut self) -> Result<Polled, String> {
+        use rumqttc::{Event, Outgoing, Packet};
+        use v5::mqttbytes::v5::Packet as PacketV5;
+
+        Ok(match self {
+            Events::V4(events) => match events.poll().await.map_err(|e| e.to_string())? {
+                Event::Incoming(Packet::ConnAck(_)) => Polled::Connected,
+                Event::Incoming(Packet::PingResp) => Polled::PingAnswered,
+                Event::Outgoing(Outgoing::PingReq) => Polled::PingSent,
+                _ => Polled::Other,
+            },
+            Events::V5(events) => match events.poll().await.map_err(|e| e.to_string())? {
+                v5::Event::Incoming(PacketV5::ConnAck(_)) => Polled::Connected,
+                v5::Event::Incoming(PacketV5::PingResp(_)) => Polled::PingAnswered,
+                v5::Event::Outgoing(Outgoing::PingReq) => Polled::PingSent,
+                _ => Polled::Other,
+            },
+        })
+    }
+}
+
+fn qos_v4(qos: u8) -> rumqttc::QoS {
+    match qos {
+        0 => rumqttc::QoS::AtMostOnce,
+        2 => rumqttc::QoS::ExactlyOnce,
+        _ => rumqttc::QoS::AtLeastOnce,
+    }
+}
+
+fn qos_v5(qos: u8) -> v5::mqttbytes::QoS {
+    match qos {
+        0 => v5::mqttbytes::QoS::AtMostOnce,
+        2 => v5::mqttbytes::QoS::ExactlyOnce,
+        _ => v5::mqttbytes::QoS::AtLeastOnce,
+    }
+}
+
+/// Client and event loop for an `mqtt://` or `mqtts://` endpoint; nothing is sent until polled
+fn connect(endpoint: &EndpointConfig, topics: &Topics) -> Result<(Client, Events), String> {
+    let config = &endpoint.mqtt;
+    let url = Url::parse(&endpoint.ws_url).map_err(|e| format!("Invalid broker URL: {}", e))?;
+    let secure = match url.scheme() {
+        "mqtt" | "tcp" => false,
+        "mqtts" | "ssl" => true,
+        scheme => return Err(format!("Unsupported broker URL scheme: {}", scheme)),
+    };
+    let host = url.host_str().ok_or("Broker URL has no host")?.to_string();
+    let port = url.port().unwrap_or(if secure { 8883 } else { 1883 });
+
+    if endpoint.proxy_url.as_deref().is_some_and(|proxy| !proxy.trim().eq_ignore_ascii_case("direct")) {
+        warn!("[{}] proxy_url isn't supported for MQTT, connecting directly", endpoint.name);
+    }
+
+    let client_id = if config.client_id.is_empty() {
+        format!("shikenmatrix-{:08x}", fastrand::u32(..))
+    } else {
+        config.client_id.clone()
+    };
+    let transport = if secure {
+        let tls_config = tls::build_client_config(&endpoint.tls)?;
+        Some(rumqttc::Transport::tls_with_config(rumqttc::TlsConfiguration::Rustls(Arc::new(tls_config))))
+    } else {
+        None
+    };
+    let keep_alive = Duration::from_secs(config.keep_alive_secs.max(5));
+    let credentials = !config.username.is_empty() || !endpoint.token.is_empty();
+
+    Ok(match config.version {
+        MqttVersion::V311 => {
+            let mut options = rumqttc::MqttOptions::new(client_id, host, port);
+            options
+                .set_keep_alive(keep_alive)
+                .set_last_will(rumqttc::LastWill::new(&topics.status, STATUS_OFFLINE, qos_v4(config.qos), true));
+            if credentials {
+                options.set_credentials(&config.username, &endpoint.token);
+            }
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+            let (client, events) = rumqttc::AsyncClient::new(options, 64);
+            (Client::V4(client), Events::V4(Box::new(events)))
+        }
+        MqttVersion::V5 => {
+            let mut options = v5::MqttOptions::new(client_id, host, port);
+            options.set_keep_alive(keep_alive).set_last_will(v5::mqttbytes::v5::LastWill::new(
+                &topics.status,
+                STATUS_OFFLINE,
+                qos_v5(config.qos),
+                true,
+                None,
+            ));
+            if credentials {
+                options.set_credentials(&config.username, &endpoint.token);
+            }
+            if let Some(transport) = transport {
+                options.set_transport(transport);
+            }
+            let (client, events) = v5::AsyncClient::new(options, 64);
+            (Client::V5(client), Events::V5(Box::new(events)))
+        }
+    })
+}
+
+/// Home Assistant discovery topics and payloads for the published state
+fn discovery_messages(config: &MqttConfig, topics: &Topics) -> Vec<(String, serde_json::Value)> {
+    let node = &config.node_id;
+    let device = json!({
+        "identifiers": [node],
+        "name": config.device_name,
+        "manufacturer": "ShikenMatrix",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    // Home Assistant rejects states longer than 255 characters
+    let entities = [
+        ("sensor", "window", "Window", &topics.window, "{{ value_json.data.title[:255] }}", "{{ value_json.data | tojson }}", "mdi:application"),
+        ("sensor", "app", "App", &topics.window, "{{ value_json.data.process_name[:255] }}", "{{ value_json.data | tojson }}", "mdi:apps"),
+        ("sensor", "media", "Media", &topics.media, "{{ (value_json.metadata.title or '')[:255] }}", "{{ value_json.metadata | tojson }}", "mdi:music"),
+        ("binary_sensor", "playing", "Playing", &topics.media, "{{ 'ON' if value_json.playback_state.playing else 'OFF' }}", "{{ value_json.playback_state | tojson }}", "mdi:play"),
+    ];
+
+    entities
+        .into_iter()
+        .map(|(component, object_id, name, state_topic, value_template, attributes_template, icon)| {
+            let topic = format!("{}/{}/{}/{}/config", config.discovery_prefix.trim_end_matches('/'), component, node, object_id);
+            let payload = json!({
+                "name": name,
+                "unique_id": format!("{}_{}", node, object_id),
+                "object_id": format!("{}_{}", node, object_id),
+                "state_topic": state_topic,
+                "value_template": value_template,
+                "json_attributes_topic": state_topic,
+                "json_attributes_template": attributes_template,
+                "availability_topic": topics.status,
+                "icon": icon,
+                "device": device,
+            });
+            (topic, payload)
+        })
+        .collect()
+}
+
+/// Connection task of an MQTT endpoint, the counterpart of `Reporter::run_reporter`
+pub(super) async fn run_mqtt(
+    config: Arc<RwLock<ReporterConfig>>,
+    index: usize,
+    mut rx: mpsc::UnboundedReceiver<ReporterMessage>,
+    artwork_urls: Arc<RwLock<ArtworkUrls>>,
+    session: Arc<Mutex<SessionState>>,
+    status: Arc<ConnectionStatus>,
+) {
+    let (queue_config, name) = config.read()
+        .map(|cfg| (cfg.queue.clone(), cfg.endpoint(index).map(|endpoint| endpoint.name).unwrap_or_default()))
+        .unwrap_or_default();
+    let mut queue = OfflineQueue::open(queue_config, &name);
+    let mut reconnect_attempts: u32 = 0;
+
+    loop {
+        let cfg = config.read().unwrap().clone();
+
+        let Some(endpoint) = cfg.endpoint(index).filter(|endpoint| endpoint.enabled) else {
+            status.set_connected(false);
+            Reporter::wait_offline(Duration::from_secs(5), &mut rx, &mut queue).await;
+            continue;
+        };
+        let mqtt = &endpoint.mqtt;
+        let topics = Topics::new(mqtt);
+        let allows = |kind: &str| endpoint.filter.allows(kind);
+        let publish = |client: &Client, entry: &QueuedMessage| match topics.for_kind(&entry.kind) {
+            Some(topic) => client.publish(topic, mqtt.qos, mqtt.retain, entry.payload.to_string()),
+            None => Ok(()),
+        };
+
+        info!("[{}] Connecting to MQTT broker {}", name, auth::redact_url_str(&endpoint.ws_url));
+
+        let error = 'connection: {
+            let (client, mut events) = match connect(&endpoint, &topics) {
+                Ok(pair) => pair,
+                Err(e) => break 'connection e,
+            };
+            let mut connected = false;
+            let mut ping_sent: Option<Instant> = None;
+
+            loop {
+                tokio::select! {
+                    event = events.poll() => match event {
+                        Ok(Polled::Connected) => {
+                            info!("[{}] ✅ Connected to MQTT broker", name);
+                            connected = true;
+                            reconnect_attempts = 0;
+                            status.set_connected(true);
+                            if let Ok(mut reconnect) = status.reconnect.write() {
+                                reconnect.attempt = 0;
+                                reconnect.next_retry_at = None;
+                            }
+
+                            let mut announcements = vec![(topics.status.clone(), STATUS_ONLINE.to_string())];
+                            if mqtt.discovery {
+                                announcements.extend(
+                                    discovery_messages(mqtt, &topics).into_iter().map(|(topic, payload)| (topic, payload.to_string())),
+                                );
+                            }
+                            for (topic, payload) in announcements {
+                                if let Err(e) = client.publish(&topic, mqtt.qos, true, payload) {
+                                    warn!("[{}] {}", name, e);
+                                }
+                            }
+
+                            // Retained topics only keep the last value, so only the newest entry of
+                            // each type is replayed; the session state is never older than the queue
+                            let mut latest: HashMap<String, QueuedMessage> = HashMap::new();
+                            let snapshot = session.lock().map(|state| state.snapshot(&artwork_urls)).unwrap_or_default();
+                            for entry in queue.drain().into_iter().chain(snapshot.iter().filter_map(ReporterMessage::to_queued)) {
+                                latest.insert(entry.kind.clone(), entry);
+                            }
+                            for entry in latest.values().filter(|entry| allows(&entry.kind)) {
+                                if let Err(e) = publish(&client, entry) {
+                                    warn!("[{}] {}", name, e);
+                                }
+                            }
+                        }
+                        Ok(Polled::PingSent) => ping_sent = Some(Instant::now()),
+                        Ok(Polled::PingAnswered) => {
+                            if let Some(sent) = ping_sent.take() {
+                                status.latency_ms.store(sent.elapsed().as_millis() as u64, Ordering::Relaxed);
+                            }
+                        }
+                        Ok(Polled::Other) => {}
+                        Err(e) => break 'connection e,
+                    },
+                    msg = rx.recv() => {
+                        let Some(msg) = msg else { return };
+                        // Artwork has no MQTT counterpart
+                        let Some(entry) = msg.to_queued() else { continue };
+                        if !allows(&entry.kind) {
+                            continue;
+                        }
+                        if !connected {
+                            queue.push(entry);
+                        } else if let Err(e) = publish(&client, &entry) {
+                            warn!("[{}] {}", name, e);
+                            queue.push(entry);
+                        }
+                    }
+                }
+            }
+        };
+
+        error!("[{}] ❌ MQTT connection failed: {}", name, auth::redact_token(&error, &endpoint.token));
+        status.set_connected(false);
+
+        reconnect_attempts = reconnect_attempts.saturating_add(1);
+        let delay = cfg.reconnect.delay(reconnect_attempts);
+        if let Ok(mut reconnect) = status.reconnect.write() {
+            reconnect.attempt = reconnect_attempts;
+            reconnect.next_retry_at = Some(unix_millis() + delay.as_millis() as u64);
+        }
+        info!("[{}] Reconnecting (attempt {}) in {:.1}s...", name, reconnect_attempts, delay.as_secs_f64());
+        Reporter::wait_offline(delay, &mut rx, &mut queue).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::reporter::Transport;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Fields of a CONNECT packet the transport sets
+    #[derive(Debug)]
+    struct ConnectPacket {
+        level: u8,
+        will_topic: String,
+        will_payload: String,
+        will_qos: u8,
+        will_retain: bool,
+        username: Option<String>,
+        password: Option<String>,
+    }
+
+    async fn read_varint(stream: &mut TcpStream) -> usize {
+        let (mut value, mut shift) = (0, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn take_varint(body: &mut &[u8]) -> usize {
+        let (mut value, mut shift) = (0, 0);
+        loop {
+            let byte = take(body, 1)[0];
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn take<'a>(body: &mut &'a [u8], len: usize) -> &'a [u8] {
+        let (head, rest) = body.split_at(len);
+        *body = rest;
+        head
+    }
+
+    fn take_string(body: &mut &[u8]) -> String {
+        let len = u16::from_be_bytes(take(body, 2).try_into().unwrap()) as usize;
+        String::from_utf8(take(body, len).to_vec()).unwrap()
+    }
+
+    /// Accept one client and decode its CONNECT without answering it
+    async fn read_connect(listener: &TcpListener) -> ConnectPacket {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(stream.read_u8().await.unwrap(), 0x10, "first packet is CONNECT");
+        let mut body = vec![0; read_varint(&mut stream).await];
+        stream.read_exact(&mut body).await.unwrap();
+
+        let mut body = body.as_slice();
+        assert_eq!(take_string(&mut body), "MQTT");
+        let level = take(&mut body, 1)[0];
+        let flags = take(&mut body, 1)[0];
+        take(&mut body, 2); // keep alive
+        if level == 5 {
+            let len = take_varint(&mut body);
+            take(&mut body, len);
+        }
+        take_string(&mut body); // client id
+        assert!(flags & 0x04 != 0, "last will is set");
+        if level == 5 {
+            let len = take_varint(&mut body);
+            take(&mut body, len);
+        }
+        let will_topic = take_string(&mut body);
+        let will_payload = take_string(&mut body);
+        let username = (flags & 0x80 != 0).then(|| take_string(&mut body));
+        let password = (flags & 0x40 != 0).then(|| take_string(&mut body));
+        ConnectPacket {
+            level,
+            will_topic,
+            will_payload,
+            will_qos: (flags >> 3) & 0x03,
+            will_retain: flags & 0x20 != 0,
+            username,
+            password,
+        }
+    }
+
+    fn endpoint(url: &str, token: &str, mqtt: MqttConfig) -> EndpointConfig {
+        let config = ReporterConfig {
+            enabled: true,
+            ws_url: url.to_string(),
+            token: token.to_string(),
+            transport: Transport::Mqtt,
+            mqtt,
+            ..ReporterConfig::default()
+        };
+        config.endpoint(0).unwrap()
+    }
+
+    /// Start the client against a local listener and return the CONNECT it sent
+    async fn connect_to_listener(token: &str, mqtt: MqttConfig) -> (Client, ConnectPacket) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("mqtt://{}", listener.local_addr().unwrap());
+        let endpoint = endpoint(&url, token, mqtt);
+        let (client, mut events) = connect(&endpoint, &Topics::new(&endpoint.mqtt)).unwrap();
+        let poll = tokio::spawn(async move { events.poll().await.map(|_| ()) });
+        let packet = tokio::time::timeout(Duration::from_secs(5), read_connect(&listener)).await.unwrap();
+        poll.abort();
+        (client, packet)
+    }
+
+    #[test]
+    fn topics_trim_the_prefix() {
+        let config = MqttConfig { topic_prefix: "home/desk/".to_string(), ..MqttConfig::default() };
+        let topics = Topics::new(&config);
+        assert_eq!(topics.window, "home/desk/window");
+        assert_eq!(topics.media, "home/desk/media");
+        assert_eq!(topics.status, "home/desk/status");
+        assert_eq!(topics.for_kind("window_info"), Some("home/desk/window"));
+        assert_eq!(topics.for_kind("media_playback"), Some("home/desk/media"));
+        assert_eq!(topics.for_kind("upload_artwork"), None);
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        let endpoint = endpoint("ws://127.0.0.1:1883", "", MqttConfig::default());
+        let Err(e) = connect(&endpoint, &Topics::new(&endpoint.mqtt)) else { panic!("ws:// accepted") };
+        assert!(e.contains("Unsupported broker URL scheme"), "{}", e);
+    }
+
+    #[test]
+    fn discovery_points_at_the_state_topics() {
+        let config = MqttConfig { discovery_prefix: "ha/".to_string(), node_id: "desk".to_string(), ..MqttConfig::default() };
+        let topics = Topics::new(&config);
+        let messages = discovery_messages(&config, &topics);
+        let (topic, payload) = &messages[0];
+        assert_eq!(topic, "ha/sensor/desk/window/config");
+        assert_eq!(payload["state_topic"], "shikenmatrix/window");
+        assert_eq!(payload["availability_topic"], "shikenmatrix/status");
+        assert!(messages.iter().any(|(topic, _)| topic == "ha/binary_sensor/desk/playing/config"));
+    }
+
+    #[tokio::test]
+    async fn v311_connect_carries_the_last_will() {
+        let mqtt = MqttConfig { username: "desk".to_string(), qos: 2, ..MqttConfig::default() };
+        let (client, packet) = connect_to_listener("secret", mqtt).await;
+        assert!(matches!(client, Client::V4(_)));
+        assert_eq!(packet.level, 4);
+        assert_eq!(packet.will_topic, "shikenmatrix/status");
+        assert_eq!(packet.will_payload, STATUS_OFFLINE);
+        assert_eq!(packet.will_qos, 2);
+        assert!(packet.will_retain);
+        assert_eq!(packet.username.as_deref(), Some("desk"));
+        assert_eq!(packet.password.as_deref(), Some("secret"));
+    }
+
+    #[tokio::test]
+    async fn v5_connect_carries_the_last_will() {
+        let mqtt = MqttConfig { version: MqttVersion::V5, topic_prefix: "desk".to_string(), ..MqttConfig::default() };
+        let (client, packet) = connect_to_listener("", mqtt).await;
+        assert!(matches!(client, Client::V5(_)));
+        assert_eq!(packet.level, 5);
+        assert_eq!(packet.will_topic, "desk/status");
+        assert_eq!(packet.will_payload, STATUS_OFFLINE);
+        assert_eq!(packet.will_qos, 1);
+        assert!(packet.will_retain);
+        assert_eq!(packet.username, None);
+        assert_eq!(packet.password, None);
+    }
+}
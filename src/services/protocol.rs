@@ -0,0 +1,108 @@
+//! Reporter wire protocol handshake
+//! The client opens every connection with `hello`; servers that understand it answer with `welcome`
+
+use serde::{Deserialize, Serialize};
+
+use super::codec::Codec;
+
+/// Version of the message format spoken by this client
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Message types this client may send
+pub const CLIENT_MESSAGE_TYPES: &[&str] = &["hello", "window_info", "media_playback", "upload_artwork_meta", "upload_artwork", "has_artwork", "command_result"];
+
+/// First message on every connection
+#[derive(Debug, Clone, Serialize)]
+pub struct HelloMessage {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub protocol_version: u32,
+    pub client_version: String,
+    /// `macos`, `windows`, `linux`, ...
+    pub os: String,
+    pub arch: String,
+    pub message_types: Vec<String>,
+    /// Features the client supports
+    pub features: Features,
+    /// Encodings the client accepts after the handshake, most preferred first
+    pub codecs: Vec<Codec>,
+}
+
+impl HelloMessage {
+    pub fn new(features: Features, codecs: Vec<Codec>) -> Self {
+        Self {
+            msg_type: "hello".to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            message_types: CLIENT_MESSAGE_TYPES.iter().map(|t| t.to_string()).collect(),
+            features,
+            codecs,
+        }
+    }
+}
+
+/// Server reply to `hello`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WelcomeMessage {
+    pub protocol_version: Option<u32>,
+    pub server_version: Option<String>,
+    /// Features the server accepts; omitted fields stay enabled
+    pub features: Features,
+    /// Encoding picked from `hello.codecs`, JSON when omitted
+    pub codec: Option<Codec>,
+}
+
+/// Optional parts of the protocol
+///
+/// Servers that never send `welcome` get everything that predates the handshake.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Features {
+    /// `media_playback` messages
+    pub media: bool,
+    /// `upload_artwork_meta` plus the binary artwork frame
+    pub artwork_upload: bool,
+    /// `has_artwork` before each upload; off unless the server asks for it
+    pub artwork_dedup: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            media: true,
+            artwork_upload: true,
+            artwork_dedup: false,
+        }
+    }
+}
+
+impl Features {
+    /// Everything this client can do, offered in `hello`
+    pub fn supported() -> Features {
+        Features {
+            artwork_dedup: true,
+            ..Features::default()
+        }
+    }
+
+    /// Whether a message of type `kind` may be sent
+    pub fn allows(&self, kind: &str) -> bool {
+        match kind {
+            "media_playback" => self.media,
+            "upload_artwork_meta" => self.artwork_upload,
+            _ => true,
+        }
+    }
+
+    /// Features both sides agreed on
+    pub fn intersect(&self, other: &Features) -> Features {
+        Features {
+            media: self.media && other.media,
+            artwork_upload: self.artwork_upload && other.artwork_upload,
+            artwork_dedup: self.artwork_dedup && other.artwork_dedup,
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Persistent offline queue for reporter messages
+//! Stores messages produced while the socket is down in ~/.shikenmatrix/queue.jsonl
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use super::reporter::DEFAULT_ENDPOINT;
+
+const QUEUE_FILE: &str = "queue.jsonl";
+
+/// What to keep while offline
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Only the most recent message of each type
+    #[default]
+    LatestPerType,
+    /// Every message, dropping the oldest once `max_entries` is reached
+    FullHistory,
+}
+
+/// Offline queue configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct QueueConfig {
+    pub enabled: bool,
+    /// Upper bound on stored messages
+    pub max_entries: usize,
+    pub retention: RetentionPolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 1000,
+            retention: RetentionPolicy::LatestPerType,
+        }
+    }
+}
+
+/// A message captured while offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedMessage {
+    /// Message type (`window_info`, `media_playback`, ...)
+    pub kind: String,
+    /// Unix milliseconds when the message was produced
+    pub timestamp: u64,
+    /// Wire payload, already carrying `timestamp`
+    pub payload: serde_json::Value,
+}
+
+/// Bounded on-disk FIFO
+pub struct OfflineQueue {
+    config: QueueConfig,
+    path: Option<PathBuf>,
+    entries: VecDeque<QueuedMessage>,
+}
+
+/// Queue file name for an endpoint; the default endpoint keeps the original name
+fn queue_file_name(endpoint: &str) -> String {
+    if endpoint == DEFAULT_ENDPOINT {
+        return QUEUE_FILE.to_string();
+    }
+    let name: String = endpoint
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("queue-{}.jsonl", name)
+}
+
+fn get_queue_path(endpoint: &str) -> Option<PathBuf> {
+    let dir = dirs::home_dir()?.join(".shikenmatrix");
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok()?;
+    }
+    Some(dir.join(queue_file_name(endpoint)))
+}
+
+impl OfflineQueue {
+    /// Open the queue of `endpoint`, loading messages left over from a previous run
+    pub fn open(config: QueueConfig, endpoint: &str) -> Self {
+        let path = if config.enabled { get_queue_path(endpoint) } else { None };
+        Self::load(config, path)
+    }
+
+    /// Queue kept in `path`, `None` keeps it in memory only
+    fn load(config: QueueConfig, path: Option<PathBuf>) -> Self {
+        let mut entries = VecDeque::new();
+        if let Some(content) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            for line in content.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str::<QueuedMessage>(line) {
+                    Ok(entry) => entries.push_back(entry),
+                    Err(e) => warn!("Skipping corrupt queue entry: {}", e),
+                }
+            }
+        }
+
+        let mut queue = Self { config, path, entries };
+        if !queue.entries.is_empty() {
+            info!("Loaded {} queued messages from disk", queue.entries.len());
+            // Config may have changed since the file was written
+            if queue.trim() {
+                queue.rewrite();
+            }
+        }
+        queue
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append a message according to the retention policy
+    pub fn push(&mut self, entry: QueuedMessage) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut rewrite = false;
+        if self.config.retention == RetentionPolicy::LatestPerType {
+            let before = self.entries.len();
+            self.entries.retain(|queued| queued.kind != entry.kind);
+            rewrite = self.entries.len() != before;
+        }
+        self.entries.push_back(entry);
+        rewrite |= self.trim();
+
+        if rewrite {
+            self.rewrite();
+        } else if let Some(last) = self.entries.back() {
+            self.append(last);
+        }
+    }
+
+    /// Take every queued message in order and clear the file
+    pub fn drain(&mut self) -> Vec<QueuedMessage> {
+        let entries: Vec<_> = self.entries.drain(..).collect();
+        if !entries.is_empty() {
+            self.rewrite();
+        }
+        entries
+    }
+
+    /// Put messages back at the front, e.g. after a failed replay
+    pub fn restore(&mut self, entries: Vec<QueuedMessage>) {
+        for entry in entries.into_iter().rev() {
+            self.entries.push_front(entry);
+        }
+        self.trim();
+        self.rewrite();
+    }
+
+    /// Drop the oldest entries beyond `max_entries`; returns whether anything was dropped
+    fn trim(&mut self) -> bool {
+        let max_entries = self.config.max_entries.max(1);
+        if self.entries.len() <= max_entries {
+            return false;
+        }
+        let overflow = self.entries.len() - max_entries;
+        self.entries.drain(..overflow);
+        true
+    }
+
+    fn append(&self, entry: &QueuedMessage) {
+        let Some(path) = &self.path else { return };
+        let result = serde_json::to_string(entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| e.to_string())?;
+                writeln!(file, "{}", line).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Failed to append to offline queue: {}", e);
+        }
+    }
+
+    fn rewrite(&self) {
+        let Some(path) = &self.path else { return };
+        let mut content = String::new();
+        for entry in &self.entries {
+            if let Ok(line) = serde_json::to_string(entry) {
+                content.push_str(&line);
+                content.push('\n');
+            }
+        }
+        if let Err(e) = fs::write(path, content) {
+            warn!("Failed to write offline queue: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Directory of its own under the temp directory, removed at the end of a test
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("shikenmatrix-queue-{}-{:08x}", std::process::id(), fastrand::u32(..)));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn queue(&self, retention: RetentionPolicy, max_entries: usize) -> OfflineQueue {
+            let config = QueueConfig { enabled: true, max_entries, retention };
+            OfflineQueue::load(config, Some(self.0.join(QUEUE_FILE)))
+        }
+
+        fn lines(&self) -> Vec<String> {
+            let content = fs::read_to_string(self.0.join(QUEUE_FILE)).unwrap_or_default();
+            content.lines().map(str::to_string).collect()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn message(kind: &str, timestamp: u64) -> QueuedMessage {
+        QueuedMessage { kind: kind.to_string(), timestamp, payload: json!({ "type": kind, "timestamp": timestamp }) }
+    }
+
+    fn timestamps<'a>(entries: impl IntoIterator<Item = &'a QueuedMessage>) -> Vec<(&'a str, u64)> {
+        entries.into_iter().map(|entry| (entry.kind.as_str(), entry.timestamp)).collect()
+    }
+
+    #[test]
+    fn latest_per_type_keeps_one_of_each_kind() {
+        let dir = TempDir::new();
+        let mut queue = dir.queue(RetentionPolicy::LatestPerType, 100);
+        queue.push(message("window_info", 1));
+        queue.push(message("media_playback", 2));
+        queue.push(message("window_info", 3));
+
+        assert_eq!(dir.lines().len(), 2);
+        assert_eq!(timestamps(&queue.drain()), [("media_playback", 2), ("window_info", 3)]);
+    }
+
+    #[test]
+    fn full_history_keeps_every_message_up_to_the_cap() {
+        let dir = TempDir::new();
+        let mut queue = dir.queue(RetentionPolicy::FullHistory, 3);
+        for timestamp in 1..=5 {
+            queue.push(message("window_info", timestamp));
+        }
+
+        // The oldest are dropped, on disk too
+        assert_eq!(queue.len(), 3);
+        assert_eq!(dir.lines().len(), 3);
+        let kept: Vec<u64> = queue.drain().iter().map(|entry| entry.timestamp).collect();
+        assert_eq!(kept, [3, 4, 5]);
+    }
+
+    #[test]
+    fn reloads_what_a_previous_run_left() {
+        let dir = TempDir::new();
+        let mut queue = dir.queue(RetentionPolicy::FullHistory, 100);
+        queue.push(message("window_info", 1));
+        queue.push(message("media_playback", 2));
+        queue.push(message("window_info", 3));
+        // Appended one line per message
+        assert_eq!(dir.lines().len(), 3);
+        drop(queue);
+
+        let mut reopened = dir.queue(RetentionPolicy::FullHistory, 100);
+        let replayed = reopened.drain();
+        assert_eq!(timestamps(&replayed), [("window_info", 1), ("media_playback", 2), ("window_info", 3)]);
+        assert_eq!(replayed[2].payload, json!({ "type": "window_info", "timestamp": 3 }));
+
+        // Draining empties the file
+        assert!(dir.lines().is_empty());
+        assert!(dir.queue(RetentionPolicy::FullHistory, 100).is_empty());
+    }
+
+    #[test]
+    fn reload_applies_a_smaller_cap_and_skips_corrupt_lines() {
+        let dir = TempDir::new();
+        let mut queue = dir.queue(RetentionPolicy::FullHistory, 100);
+        for timestamp in 1..=4 {
+            queue.push(message("window_info", timestamp));
+        }
+        let mut content = fs::read_to_string(dir.0.join(QUEUE_FILE)).unwrap();
+        content.push_str("{ not json\n");
+        fs::write(dir.0.join(QUEUE_FILE), content).unwrap();
+
+        let reopened = dir.queue(RetentionPolicy::FullHistory, 2);
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(dir.lines().len(), 2);
+        assert_eq!(timestamps(&reopened.entries), [("window_info", 3), ("window_info", 4)]);
+    }
+
+    #[test]
+    fn restore_puts_messages_back_in_front() {
+        let dir = TempDir::new();
+        let mut queue = dir.queue(RetentionPolicy::FullHistory, 100);
+        queue.push(message("window_info", 1));
+        queue.push(message("media_playback", 2));
+        let unsent = queue.drain();
+        queue.push(message("window_info", 3));
+        queue.restore(unsent);
+
+        assert_eq!(dir.lines().len(), 3);
+        assert_eq!(timestamps(&queue.drain()), [("window_info", 1), ("media_playback", 2), ("window_info", 3)]);
+    }
+
+    #[test]
+    fn disabled_queue_keeps_nothing() {
+        let mut queue = OfflineQueue::open(QueueConfig { enabled: false, ..QueueConfig::default() }, DEFAULT_ENDPOINT);
+        queue.push(message("window_info", 1));
+        assert!(queue.is_empty());
+        assert!(queue.path.is_none());
+    }
+
+    #[test]
+    fn file_name_per_endpoint() {
+        assert_eq!(queue_file_name(DEFAULT_ENDPOINT), "queue.jsonl");
+        assert_eq!(queue_file_name("team"), "queue-team.jsonl");
+        assert_eq!(queue_file_name("home server/2"), "queue-home_server_2.jsonl");
+    }
+}
@@ -0,0 +1,112 @@
+//! Reconnect policy for the reporter connection
+//! Exponential backoff with full jitter so clients don't reconnect in lockstep
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Backoff settings, configurable under `[reporter.reconnect]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    /// Delay before the first retry
+    pub min_delay_ms: u64,
+    /// Upper bound for any single delay
+    pub max_delay_ms: u64,
+    /// Growth factor per failed attempt
+    pub multiplier: f64,
+    /// Pick a random delay between zero and the backoff value (full jitter)
+    pub jitter: bool,
+    /// A connection that stays up this long resets the attempt counter
+    pub reset_after_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            min_delay_ms: 1_000,
+            max_delay_ms: 60_000,
+            multiplier: 2.0,
+            jitter: true,
+            reset_after_ms: 30_000,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before retry number `attempt` (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let min = self.min_delay_ms.min(self.max_delay_ms);
+        let exponent = attempt.saturating_sub(1).min(63) as i32;
+        let backoff = (min as f64 * self.multiplier.max(1.0).powi(exponent)).min(self.max_delay_ms as f64) as u64;
+        let backoff = backoff.max(min);
+
+        let delay = if self.jitter {
+            fastrand::u64(0..=backoff)
+        } else {
+            backoff
+        };
+        Duration::from_millis(delay)
+    }
+
+    pub fn reset_after(&self) -> Duration {
+        Duration::from_millis(self.reset_after_ms)
+    }
+}
+
+/// Reconnect progress, exposed through the status API
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconnectStatus {
+    /// Failed attempts since the last stable connection
+    pub attempt: u32,
+    /// Unix milliseconds of the next retry, `None` while connected or connecting
+    pub next_retry_at: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_policy(jitter: bool) -> ReconnectPolicy {
+        ReconnectPolicy {
+            min_delay_ms: 100,
+            max_delay_ms: 1_000,
+            multiplier: 2.0,
+            jitter,
+            reset_after_ms: 30_000,
+        }
+    }
+
+    #[test]
+    fn delay_grows_until_the_cap() {
+        let policy = test_policy(false);
+        let delays: Vec<u64> = (1..=6).map(|attempt| policy.delay(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1_000, 1_000]);
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn full_jitter_stays_within_zero_and_backoff() {
+        let policy = test_policy(true);
+        for attempt in 1..=8 {
+            let backoff = test_policy(false).delay(attempt);
+            for _ in 0..200 {
+                assert!(policy.delay(attempt) <= backoff);
+            }
+        }
+        // The first retry is spread out too
+        let first: Vec<Duration> = (0..200).map(|_| policy.delay(1)).collect();
+        assert!(first.iter().any(|delay| *delay < Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn min_above_max_is_capped() {
+        let policy = ReconnectPolicy { min_delay_ms: 5_000, ..test_policy(false) };
+        assert_eq!(policy.delay(1), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn multiplier_below_one_keeps_the_minimum() {
+        let policy = ReconnectPolicy { multiplier: 0.5, ..test_policy(false) };
+        assert_eq!(policy.delay(4), Duration::from_millis(100));
+    }
+}
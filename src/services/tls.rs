@@ -0,0 +1,471 @@
+//! TLS settings for the reporter connection
+//! Trust anchors (bundled, system, extra PEM), client certificates for mTLS and SPKI pinning
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// TLS configuration, under `[reporter.tls]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Trust the Mozilla roots bundled with the client
+    pub webpki_roots: bool,
+    /// Trust the operating system certificate store
+    pub native_roots: bool,
+    /// Extra PEM files with root certificates (e.g. an internal CA)
+    pub ca_files: Vec<PathBuf>,
+    /// PEM client certificate chain for mutual TLS
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key matching `client_cert`
+    pub client_key: Option<PathBuf>,
+    /// Base64 SHA-256 digests of accepted SubjectPublicKeyInfo, empty disables pinning
+    ///
+    /// Same format as `pin-sha256` in HPKP; matching any certificate of the verified chain,
+    /// its trust anchor included, is enough.
+    pub spki_pins: Vec<String>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            webpki_roots: true,
+            native_roots: false,
+            ca_files: Vec::new(),
+            client_cert: None,
+            client_key: None,
+            spki_pins: Vec::new(),
+        }
+    }
+}
+
+/// Expand a leading `~/` to the home directory
+fn expand_path(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let path = expand_path(path);
+    let certs = CertificateDer::pem_file_iter(&path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read certificates from {}: {}", path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    let path = expand_path(path);
+    PrivateKeyDer::from_pem_file(&path)
+        .map_err(|e| format!("Failed to read private key from {}: {}", path.display(), e))
+}
+
+fn root_store(config: &TlsConfig) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+
+    if config.webpki_roots {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+
+    if config.native_roots {
+        let native = rustls_native_certs::load_native_certs();
+        for e in &native.errors {
+            warn!("Error loading system certificates: {}", e);
+        }
+        let (added, ignored) = roots.add_parsable_certificates(native.certs);
+        info!("Loaded {} system root certificates ({} ignored)", added, ignored);
+    }
+
+    for path in &config.ca_files {
+        for cert in load_certs(path)? {
+            roots
+                .add(cert)
+                .map_err(|e| format!("Invalid CA certificate in {}: {}", path.display(), e))?;
+        }
+    }
+
+    if roots.is_empty() {
+        return Err("No trusted root certificates configured".to_string());
+    }
+    Ok(roots)
+}
+
+/// Build the rustls client configuration for the reporter
+pub fn build_client_config(config: &TlsConfig) -> Result<rustls::ClientConfig, String> {
+    let roots = Arc::new(root_store(config)?);
+
+    let builder = if config.spki_pins.is_empty() {
+        rustls::ClientConfig::builder().with_root_certificates(roots)
+    } else {
+        let verifier = PinnedVerifier::new(roots, &config.spki_pins)?;
+        rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+    };
+
+    match (&config.client_cert, &config.client_key) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(load_certs(cert)?, load_key(key)?)
+            .map_err(|e| format!("Invalid client certificate: {}", e)),
+        (None, None) => Ok(builder.with_no_client_auth()),
+        _ => Err("client_cert and client_key must be set together".to_string()),
+    }
+}
+
+/// Base64 SHA-256 of a certificate's SubjectPublicKeyInfo
+pub fn spki_sha256(cert: &CertificateDer<'_>) -> Result<String, String> {
+    let cert = webpki::EndEntityCert::try_from(cert).map_err(|e| format!("Invalid certificate: {:?}", e))?;
+    Ok(digest(cert.subject_public_key_info().as_ref()))
+}
+
+fn digest(spki: &[u8]) -> String {
+    STANDARD.encode(Sha256::digest(spki))
+}
+
+/// DER `SEQUENCE` around `contents`; trust anchors keep their SPKI without it
+fn der_sequence(contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x30];
+    let len = contents.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+/// Regular WebPKI verification followed by an SPKI pin check on the verified chain
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    roots: Arc<RootCertStore>,
+    pins: Vec<String>,
+}
+
+impl PinnedVerifier {
+    fn new(roots: Arc<RootCertStore>, pins: &[String]) -> Result<Self, String> {
+        let inner = WebPkiServerVerifier::builder(roots.clone())
+            .build()
+            .map_err(|e| format!("Failed to build certificate verifier: {}", e))?;
+        let pins = pins.iter().map(|pin| pin.trim().trim_start_matches("sha256/").to_string()).collect();
+        Ok(Self { inner, roots, pins })
+    }
+
+    /// Whether a certificate of the path, or its anchor, carries a pinned key
+    fn is_pinned(&self, path: &webpki::VerifiedPath<'_>) -> bool {
+        let end_entity = path.end_entity().subject_public_key_info();
+        let intermediates = path.intermediate_certificates().map(|cert| cert.subject_public_key_info());
+        let anchor = der_sequence(path.anchor().subject_public_key_info.as_ref());
+        std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|spki| digest(spki.as_ref()))
+            .chain(std::iter::once(digest(&anchor)))
+            .any(|digest| self.pins.contains(&digest))
+    }
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self
+            .inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+
+        // The certificates sent are unverified, an extra one could carry any key; only a chain
+        // that actually validates counts, and path building tries the others if one isn't pinned
+        let cert = webpki::EndEntityCert::try_from(end_entity).map_err(|e| rustls::Error::General(format!("{:?}", e)))?;
+        let pinned = cert
+            .verify_for_usage(
+                webpki::ALL_VERIFICATION_ALGS,
+                &self.roots.roots,
+                intermediates,
+                now,
+                webpki::KeyUsage::server_auth(),
+                None,
+                Some(&|path| if self.is_pinned(path) { Ok(()) } else { Err(webpki::Error::UnknownIssuer) }),
+            )
+            .is_ok();
+        if !pinned {
+            warn!("Server certificate for {:?} matches none of the configured SPKI pins", server_name);
+            return Err(rustls::Error::General("certificate does not match any SPKI pin".to_string()));
+        }
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair, PublicKeyData};
+    use rustls::server::WebPkiClientVerifier;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+    /// A private CA with a `localhost` server certificate and a client certificate, as PEM files
+    struct Pki {
+        dir: PathBuf,
+        ca: CertificateDer<'static>,
+        server_chain: Vec<CertificateDer<'static>>,
+        server_key: PrivateKeyDer<'static>,
+        server_spki: Vec<u8>,
+    }
+
+    impl Pki {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("shikenmatrix-tls-{}-{:08x}", std::process::id(), fastrand::u32(..)));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = CertifiedIssuer::self_signed(ca_params, KeyPair::generate().unwrap()).unwrap();
+
+            let server_key = KeyPair::generate().unwrap();
+            let server = CertificateParams::new(vec!["localhost".to_string()]).unwrap().signed_by(&server_key, &ca).unwrap();
+            let client_key = KeyPair::generate().unwrap();
+            let client = CertificateParams::new(vec!["reporter".to_string()]).unwrap().signed_by(&client_key, &ca).unwrap();
+
+            std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+            std::fs::write(dir.join("client.pem"), client.pem()).unwrap();
+            std::fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+
+            Self {
+                ca: ca.der().clone(),
+                server_chain: vec![server.der().clone(), ca.der().clone()],
+                server_key: PrivateKeyDer::try_from(server_key.serialize_der()).unwrap(),
+                server_spki: server_key.subject_public_key_info(),
+                dir,
+            }
+        }
+
+        /// Only the private CA is trusted
+        fn trusting_ca(&self) -> TlsConfig {
+            TlsConfig { webpki_roots: false, ca_files: vec![self.dir.join("ca.pem")], ..TlsConfig::default() }
+        }
+
+        /// Accept one connection and greet; with `require_client_cert` the client must present a certificate from the CA
+        async fn serve(&self, require_client_cert: bool) -> (u16, tokio::task::JoinHandle<Result<usize, String>>) {
+            self.serve_chain(self.server_chain.clone(), self.server_key.clone_key(), require_client_cert).await
+        }
+
+        /// Like `serve`, presenting `chain` as sent instead of the CA's own server certificate
+        async fn serve_chain(
+            &self,
+            chain: Vec<CertificateDer<'static>>,
+            key: PrivateKeyDer<'static>,
+            require_client_cert: bool,
+        ) -> (u16, tokio::task::JoinHandle<Result<usize, String>>) {
+            let builder = rustls::ServerConfig::builder();
+            let builder = if require_client_cert {
+                let mut roots = RootCertStore::empty();
+                roots.add(self.ca.clone()).unwrap();
+                builder.with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).build().unwrap())
+            } else {
+                builder.with_no_client_auth()
+            };
+            let config = builder.with_single_cert(chain, key).unwrap();
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+                let mut stream = TlsAcceptor::from(Arc::new(config)).accept(stream).await.map_err(|e| e.to_string())?;
+                let client_certs = stream.get_ref().1.peer_certificates().map_or(0, |certs| certs.len());
+                stream.write_all(b"hello").await.map_err(|e| e.to_string())?;
+                stream.shutdown().await.map_err(|e| e.to_string())?;
+                Ok(client_certs)
+            });
+            (port, server)
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Connect to `localhost:port` with `config` and read the greeting
+    async fn connect(config: &TlsConfig, port: u16) -> Result<String, String> {
+        let connector = TlsConnector::from(Arc::new(build_client_config(config)?));
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.map_err(|e| e.to_string())?;
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(server_name, stream).await.map_err(|e| e.to_string())?;
+        let mut greeting = String::new();
+        stream.read_to_string(&mut greeting).await.map_err(|e| e.to_string())?;
+        Ok(greeting)
+    }
+
+    fn pin(spki: &[u8]) -> String {
+        STANDARD.encode(Sha256::digest(spki))
+    }
+
+    #[test]
+    fn spki_digest_covers_the_public_key() {
+        let pki = Pki::new();
+        assert_eq!(spki_sha256(&pki.server_chain[0]).unwrap(), pin(&pki.server_spki));
+        assert!(spki_sha256(&CertificateDer::from(vec![0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn client_cert_needs_a_key() {
+        let pki = Pki::new();
+        let config = TlsConfig { client_cert: Some(pki.dir.join("client.pem")), ..pki.trusting_ca() };
+        let Err(e) = build_client_config(&config) else { panic!("accepted a certificate without key") };
+        assert!(e.contains("must be set together"), "{}", e);
+    }
+
+    #[test]
+    fn no_roots_is_an_error() {
+        let config = TlsConfig { webpki_roots: false, ..TlsConfig::default() };
+        assert!(build_client_config(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn trusts_ca_files() {
+        let pki = Pki::new();
+        let (port, server) = pki.serve(false).await;
+        assert_eq!(connect(&pki.trusting_ca(), port).await.unwrap(), "hello");
+        assert_eq!(server.await.unwrap().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn webpki_roots_reject_a_private_ca() {
+        let pki = Pki::new();
+        let (port, server) = pki.serve(false).await;
+        let e = connect(&TlsConfig::default(), port).await.unwrap_err();
+        assert!(e.contains("UnknownIssuer"), "{}", e);
+        assert!(server.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn pin_of_the_leaf_or_the_ca_matches() {
+        let pki = Pki::new();
+        for pin in [pin(&pki.server_spki), format!("sha256/{}", spki_sha256(&pki.ca).unwrap())] {
+            let (port, server) = pki.serve(false).await;
+            let config = TlsConfig { spki_pins: vec![pin], ..pki.trusting_ca() };
+            assert_eq!(connect(&config, port).await.unwrap(), "hello");
+            server.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn pin_mismatch_is_rejected() {
+        let pki = Pki::new();
+        let (port, server) = pki.serve(false).await;
+        let other = KeyPair::generate().unwrap().subject_public_key_info();
+        let config = TlsConfig { spki_pins: vec![pin(&other)], ..pki.trusting_ca() };
+        let e = connect(&config, port).await.unwrap_err();
+        assert!(e.contains("SPKI pin"), "{}", e);
+        assert!(server.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn pinned_ca_appended_to_another_chain_is_rejected() {
+        let (pki, attacker) = (Pki::new(), Pki::new());
+        // Both CAs are trusted, but only the pinned one may have issued the server certificate
+        let chain = vec![attacker.server_chain[0].clone(), pki.ca.clone()];
+        let (port, server) = pki.serve_chain(chain, attacker.server_key.clone_key(), false).await;
+        let config = TlsConfig {
+            spki_pins: vec![spki_sha256(&pki.ca).unwrap()],
+            ca_files: vec![pki.dir.join("ca.pem"), attacker.dir.join("ca.pem")],
+            ..pki.trusting_ca()
+        };
+        let e = connect(&config, port).await.unwrap_err();
+        assert!(e.contains("SPKI pin"), "{}", e);
+        assert!(server.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn pin_of_a_root_the_server_does_not_send_matches() {
+        let pki = Pki::new();
+        let chain = vec![pki.server_chain[0].clone()];
+        let (port, server) = pki.serve_chain(chain, pki.server_key.clone_key(), false).await;
+        let config = TlsConfig { spki_pins: vec![spki_sha256(&pki.ca).unwrap()], ..pki.trusting_ca() };
+        assert_eq!(connect(&config, port).await.unwrap(), "hello");
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn der_sequence_encodes_long_lengths() {
+        assert_eq!(der_sequence(&[1, 2]), [0x30, 2, 1, 2]);
+        let long = der_sequence(&[0; 300]);
+        assert_eq!(long[..4], [0x30, 0x82, 0x01, 0x2c]);
+        assert_eq!(long.len(), 304);
+    }
+
+    #[tokio::test]
+    async fn pin_does_not_replace_chain_validation() {
+        let pki = Pki::new();
+        let (port, server) = pki.serve(false).await;
+        let config = TlsConfig { spki_pins: vec![pin(&pki.server_spki)], ..TlsConfig::default() };
+        let e = connect(&config, port).await.unwrap_err();
+        assert!(e.contains("UnknownIssuer"), "{}", e);
+        assert!(server.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn presents_the_client_certificate() {
+        let pki = Pki::new();
+        let (port, server) = pki.serve(true).await;
+        let config = TlsConfig {
+            client_cert: Some(pki.dir.join("client.pem")),
+            client_key: Some(pki.dir.join("client.key")),
+            ..pki.trusting_ca()
+        };
+        assert_eq!(connect(&config, port).await.unwrap(), "hello");
+        assert_eq!(server.await.unwrap().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn server_requiring_a_client_certificate_rejects_none() {
+        let pki = Pki::new();
+        let (port, server) = pki.serve(true).await;
+        assert!(connect(&pki.trusting_ca(), port).await.is_err());
+        let e = server.await.unwrap().unwrap_err();
+        assert!(e.contains("certificate"), "{}", e);
+    }
+}
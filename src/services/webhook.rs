@@ -0,0 +1,598 @@
+//! HTTP webhook transport
+//! POSTs the same JSON messages as the WebSocket transport, for backends that can't hold a socket
+//!
+//! `window_info` / `media_playback` are sent as `application/json`. Artwork is sent as
+//! `multipart/form-data` with a `meta` field (the `upload_artwork_meta` JSON) and an
+//! `artwork` file field; the server may answer with the usual `artwork_uploaded` message.
+//!
+//! With `signing_secret` set, every request carries `X-ShikenMatrix-Signature: sha256=<hex>`,
+//! the HMAC-SHA256 of the JSON body, or of the `meta` field followed by the image bytes.
+
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::multipart::{Form, Part};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::platform::unix_millis;
+use super::artwork::ArtworkUrls;
+use super::auth::{self, AuthMode};
+use super::proxy;
+use super::queue::{OfflineQueue, QueuedMessage};
+use super::reconnect::ReconnectPolicy;
+use super::reporter::{
+    handle_server_text, ConnectionStatus, EndpointConfig, Reporter, ReporterConfig, ReporterMessage, ServerAction,
+    SessionState, UploadArtworkMetaMessage,
+};
+use super::tls;
+
+/// Header carrying the request signature
+pub const SIGNATURE_HEADER: &str = "X-ShikenMatrix-Signature";
+
+/// Webhook settings, under `[reporter.webhook]` or `[reporter.endpoints.webhook]`
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// Extra request headers, e.g. an API key
+    pub headers: BTreeMap<String, String>,
+    /// Minimum time between two flushes; updates in between are coalesced to the latest per type
+    pub min_interval_ms: u64,
+    /// Timeout of a single request
+    pub timeout_ms: u64,
+    /// Retries of a failed request before it goes to the offline queue
+    pub max_retries: u32,
+    /// Backoff between those retries
+    pub retry: ReconnectPolicy,
+    /// Multipart target for artwork, defaults to the endpoint URL
+    pub artwork_url: Option<String>,
+    /// HMAC-SHA256 key for the signature header, unsigned when unset
+    pub signing_secret: Option<String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            headers: BTreeMap::new(),
+            min_interval_ms: 1_000,
+            timeout_ms: 10_000,
+            max_retries: 3,
+            retry: ReconnectPolicy {
+                min_delay_ms: 500,
+                max_delay_ms: 5_000,
+                ..ReconnectPolicy::default()
+            },
+            artwork_url: None,
+            signing_secret: None,
+        }
+    }
+}
+
+// Header values often carry credentials, only their names are printed
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("min_interval_ms", &self.min_interval_ms)
+            .field("timeout_ms", &self.timeout_ms)
+            .field("max_retries", &self.max_retries)
+            .field("retry", &self.retry)
+            .field("artwork_url", &self.artwork_url.as_deref().map(auth::redact_url_str))
+            .field("signing_secret", &self.signing_secret.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// Data for one request
+enum Outgoing {
+    /// `window_info` / `media_playback` payload, from the channel or the offline queue
+    Json(QueuedMessage),
+    Artwork {
+        content_item_identifier: String,
+        hash: String,
+        artwork_data: Arc<Vec<u8>>,
+        mime_type: String,
+    },
+}
+
+impl Outgoing {
+    fn from_message(msg: ReporterMessage) -> Option<Self> {
+        match msg {
+            ReporterMessage::UploadArtwork { content_item_identifier, hash, artwork_data, mime_type } => {
+                Some(Outgoing::Artwork { content_item_identifier, hash, artwork_data, mime_type })
+            }
+            msg => msg.to_queued().map(Outgoing::Json),
+        }
+    }
+
+    fn kind(&self) -> &str {
+        match self {
+            Outgoing::Json(entry) => &entry.kind,
+            Outgoing::Artwork { .. } => "upload_artwork_meta",
+        }
+    }
+
+    /// Whether `self` makes `other` obsolete
+    fn supersedes(&self, other: &Outgoing) -> bool {
+        match (self, other) {
+            (Outgoing::Json(new), Outgoing::Json(old)) => new.kind == old.kind,
+            (
+                Outgoing::Artwork { content_item_identifier: new, .. },
+                Outgoing::Artwork { content_item_identifier: old, .. },
+            ) => new == old,
+            _ => false,
+        }
+    }
+}
+
+/// Queue `outgoing`, replacing an older update of the same type
+fn push_pending(pending: &mut VecDeque<Outgoing>, outgoing: Outgoing) {
+    pending.retain(|queued| !outgoing.supersedes(queued));
+    pending.push_back(outgoing);
+}
+
+enum Failure {
+    /// Network error, timeout, 5xx or 429; worth another attempt
+    Retryable(String),
+    /// Any other non-success status; the request is dropped
+    Rejected(String),
+}
+
+/// HTTP client honoring the endpoint's TLS and proxy settings
+pub(super) fn http_client(endpoint: &EndpointConfig, target: &Url, timeout: Duration) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .use_preconfigured_tls(tls::build_client_config(&endpoint.tls)?)
+        .timeout(timeout)
+        .user_agent(concat!("ShikenMatrix/", env!("CARGO_PKG_VERSION")));
+    builder = match proxy::resolve(endpoint.proxy_url.as_deref(), target)? {
+        Some(proxy_url) => builder.proxy(
+            reqwest::Proxy::all(proxy_url.as_str()).map_err(|e| format!("Invalid proxy: {}", e.without_url()))?,
+        ),
+        None => builder.no_proxy(),
+    };
+    builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Error message with its causes; the URL may carry the token, so it is left out
+pub(super) fn describe_error(error: reqwest::Error) -> String {
+    let error = error.without_url();
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// `sha256=<hex>` HMAC of `parts` in order, the value of [`SIGNATURE_HEADER`]
+pub fn signature(secret: &str, parts: &[&[u8]]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    let hex: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Resolved request settings of an endpoint
+struct Target {
+    client: reqwest::Client,
+    url: Url,
+    artwork_url: Url,
+    headers: HeaderMap,
+    token: String,
+    signing_secret: Option<String>,
+}
+
+impl Target {
+    fn new(endpoint: &EndpointConfig) -> Result<Self, String> {
+        let webhook = &endpoint.webhook;
+        let mut url = Url::parse(&endpoint.ws_url).map_err(|e| format!("Invalid webhook URL: {}", e))?;
+        let mut artwork_url = match &webhook.artwork_url {
+            Some(artwork_url) => Url::parse(artwork_url).map_err(|e| format!("Invalid artwork URL: {}", e))?,
+            None => url.clone(),
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &webhook.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("Invalid header name: {}", name))?;
+            let value = HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header {}", name))?;
+            headers.insert(name, value);
+        }
+
+        let mut token = String::new();
+        if !endpoint.token.is_empty() {
+            match endpoint.auth_mode {
+                AuthMode::Query => {
+                    url.query_pairs_mut().append_pair("token", &endpoint.token);
+                    artwork_url.query_pairs_mut().append_pair("token", &endpoint.token);
+                }
+                AuthMode::Header => token = endpoint.token.clone(),
+                AuthMode::Subprotocol => {
+                    warn!("Subprotocol auth doesn't apply to webhooks, sending an Authorization header instead");
+                    token = endpoint.token.clone();
+                }
+            }
+        }
+
+        let client = http_client(endpoint, &url, Duration::from_millis(webhook.timeout_ms))?;
+        let signing_secret = webhook.signing_secret.clone().filter(|secret| !secret.is_empty());
+        Ok(Self { client, url, artwork_url, headers, token, signing_secret })
+    }
+
+    /// Request with the endpoint's headers, signed over `signed` when a secret is set
+    fn post(&self, url: &Url, signed: &[&[u8]]) -> reqwest::RequestBuilder {
+        let mut request = self.client.post(url.clone()).headers(self.headers.clone());
+        if !self.token.is_empty() {
+            request = request.bearer_auth(&self.token);
+        }
+        if let Some(secret) = &self.signing_secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, signed));
+        }
+        request
+    }
+
+    /// Send once; the response body is returned on success
+    async fn send(&self, outgoing: &Outgoing) -> Result<String, Failure> {
+        let request = match outgoing {
+            Outgoing::Json(entry) => {
+                let body = serde_json::to_vec(&entry.payload).map_err(|e| Failure::Rejected(e.to_string()))?;
+                self.post(&self.url, &[&body]).header(CONTENT_TYPE, "application/json").body(body)
+            }
+            Outgoing::Artwork { content_item_identifier, hash, artwork_data, mime_type } => {
+                let meta = UploadArtworkMetaMessage::new(content_item_identifier.clone(), hash.clone(), mime_type.clone());
+                let meta = serde_json::to_string(&meta).map_err(|e| Failure::Rejected(e.to_string()))?;
+                let part = Part::bytes(artwork_data.to_vec())
+                    .file_name("artwork")
+                    .mime_str(mime_type)
+                    .map_err(|e| Failure::Rejected(format!("Invalid artwork MIME type: {}", e)))?;
+                self.post(&self.artwork_url, &[meta.as_bytes(), artwork_data])
+                    .multipart(Form::new().text("meta", meta).part("artwork", part))
+            }
+        };
+
+        let response = request.send().await.map_err(|e| Failure::Retryable(self.describe(e)))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(response.text().await.unwrap_or_default())
+        } else if status.is_server_error()
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT
+        {
+            Err(Failure::Retryable(format!("HTTP {}", status)))
+        } else {
+            Err(Failure::Rejected(format!("HTTP {}", status)))
+        }
+    }
+
+    fn describe(&self, error: reqwest::Error) -> String {
+        auth::redact_token(&describe_error(error), &self.token)
+    }
+
+    async fn send_with_retry(&self, name: &str, outgoing: &Outgoing, config: &WebhookConfig) -> Result<String, Failure> {
+        let mut attempt = 0;
+        loop {
+            match self.send(outgoing).await {
+                Err(Failure::Retryable(e)) if attempt < config.max_retries => {
+                    attempt += 1;
+                    let delay = config.retry.delay(attempt);
+                    warn!("[{}] Webhook request failed ({}), retry {} in {:.1}s", name, e, attempt, delay.as_secs_f64());
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Connection task of a webhook endpoint, the counterpart of `Reporter::run_reporter`
+pub(super) async fn run_webhook(
+    config: Arc<RwLock<ReporterConfig>>,
+    index: usize,
+    mut rx: mpsc::UnboundedReceiver<ReporterMessage>,
+    artwork_urls: Arc<RwLock<ArtworkUrls>>,
+    session: Arc<Mutex<SessionState>>,
+    status: Arc<ConnectionStatus>,
+) {
+    let (queue_config, name) = config.read()
+        .map(|cfg| (cfg.queue.clone(), cfg.endpoint(index).map(|endpoint| endpoint.name).unwrap_or_default()))
+        .unwrap_or_default();
+    let mut queue = OfflineQueue::open(queue_config, &name);
+
+    let mut pending: VecDeque<Outgoing> = VecDeque::new();
+    // Client is rebuilt only when the endpoint settings change
+    let mut target: Option<(String, Target)> = None;
+    let mut last_flush: Option<tokio::time::Instant> = None;
+    let mut failures: u32 = 0;
+
+    loop {
+        let cfg = config.read().unwrap().clone();
+
+        let Some(endpoint) = cfg.endpoint(index).filter(|endpoint| endpoint.enabled) else {
+            status.set_connected(false);
+            Reporter::wait_offline(Duration::from_secs(5), &mut rx, &mut queue).await;
+            continue;
+        };
+        let webhook = &endpoint.webhook;
+
+        // Wait for work; after a failure the retry is due now, the backoff has already passed
+        if pending.is_empty() && queue.is_empty() && failures == 0 {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+                Ok(Some(msg)) => pending.extend(Outgoing::from_message(msg)),
+                Ok(None) => return,
+                Err(_) => continue,
+            }
+        }
+
+        // Coalesce updates until the minimum interval has passed
+        if let Some(last_flush) = last_flush {
+            let ready = last_flush + Duration::from_millis(webhook.min_interval_ms);
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(ready) => break,
+                    Some(msg) = rx.recv() => {
+                        if let Some(outgoing) = Outgoing::from_message(msg) {
+                            push_pending(&mut pending, outgoing);
+                        }
+                    }
+                }
+            }
+        }
+        while let Ok(msg) = rx.try_recv() {
+            if let Some(outgoing) = Outgoing::from_message(msg) {
+                push_pending(&mut pending, outgoing);
+            }
+        }
+        last_flush = Some(tokio::time::Instant::now());
+
+        let key = serde_json::to_string(&endpoint).unwrap_or_default();
+        if target.as_ref().is_none_or(|(current, _)| *current != key) {
+            match Target::new(&endpoint) {
+                Ok(new_target) => {
+                    info!("[{}] Webhook target: {}", name, auth::redact_url(&new_target.url));
+                    target = Some((key, new_target));
+                }
+                Err(e) => {
+                    error!("[{}] {}", name, auth::redact_token(&e, &endpoint.token));
+                    target = None;
+                    for outgoing in pending.drain(..) {
+                        if let Outgoing::Json(entry) = outgoing {
+                            queue.push(entry);
+                        }
+                    }
+                    Reporter::wait_offline(Duration::from_secs(5), &mut rx, &mut queue).await;
+                    continue;
+                }
+            }
+        }
+        let Some((_, target)) = target.as_ref() else { continue };
+
+        // Messages parked during an outage go first, in order
+        let mut batch: VecDeque<Outgoing> = queue.drain().into_iter().map(Outgoing::Json).collect();
+        if failures > 0 {
+            // State the queue didn't keep (e.g. queue disabled) and unacknowledged artwork
+            let covered: HashSet<String> = batch.iter().chain(pending.iter()).map(|o| o.kind().to_string()).collect();
+            let snapshot = session.lock().map(|state| state.snapshot(&artwork_urls)).unwrap_or_default();
+            for outgoing in snapshot.into_iter().filter_map(Outgoing::from_message) {
+                let superseded = pending.iter().any(|newer| newer.supersedes(&outgoing));
+                if matches!(outgoing, Outgoing::Artwork { .. }) && !superseded || !covered.contains(outgoing.kind()) {
+                    batch.push_back(outgoing);
+                }
+            }
+        }
+        batch.extend(pending.drain(..));
+
+        let mut result = Ok(());
+        while let Some(outgoing) = batch.pop_front() {
+            if !endpoint.filter.allows(outgoing.kind()) {
+                continue;
+            }
+            let started = Instant::now();
+            match target.send_with_retry(&name, &outgoing, webhook).await {
+                Ok(body) => {
+                    status.latency_ms.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+                    // An artwork acknowledgement may complete the current media message
+                    if let Some(ServerAction::ResendMedia(media)) = handle_server_text(&body, &session, &artwork_urls, &status) {
+                        batch.extend(Outgoing::from_message(ReporterMessage::MediaPlayback(media)));
+                    }
+                }
+                Err(Failure::Rejected(e)) => {
+                    warn!("[{}] Webhook rejected {}: {}", name, outgoing.kind(), e);
+                }
+                Err(Failure::Retryable(e)) => {
+                    batch.push_front(outgoing);
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        match result {
+            Ok(()) => {
+                if failures > 0 {
+                    info!("[{}] ✅ Webhook reachable again", name);
+                }
+                failures = 0;
+                status.set_connected(true);
+                if let Ok(mut reconnect) = status.reconnect.write() {
+                    reconnect.attempt = 0;
+                    reconnect.next_retry_at = None;
+                }
+            }
+            Err(e) => {
+                error!("[{}] ❌ Webhook delivery failed: {}", name, e);
+                status.set_connected(false);
+
+                // Undelivered updates wait in the offline queue; artwork stays pending in the session
+                for outgoing in batch {
+                    if let Outgoing::Json(entry) = outgoing {
+                        queue.push(entry);
+                    }
+                }
+
+                failures = failures.saturating_add(1);
+                let delay = cfg.reconnect.delay(failures);
+                if let Ok(mut reconnect) = status.reconnect.write() {
+                    reconnect.attempt = failures;
+                    reconnect.next_retry_at = Some(unix_millis() + delay.as_millis() as u64);
+                }
+                info!("[{}] Retrying webhook (attempt {}) in {:.1}s...", name, failures, delay.as_secs_f64());
+                Reporter::wait_offline(delay, &mut rx, &mut queue).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::reporter::Transport;
+    use crate::services::test_http::serve;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    fn endpoint(addr: std::net::SocketAddr, webhook: WebhookConfig) -> EndpointConfig {
+        let config = ReporterConfig {
+            enabled: true,
+            ws_url: format!("http://{}/hook", addr),
+            token: "secret-token".to_string(),
+            auth_mode: AuthMode::Header,
+            proxy_url: Some("direct".to_string()),
+            transport: Transport::Webhook,
+            webhook,
+            ..ReporterConfig::default()
+        };
+        config.endpoint(0).unwrap()
+    }
+
+    fn window_info() -> Outgoing {
+        Outgoing::Json(QueuedMessage {
+            kind: "window_info".to_string(),
+            timestamp: 1,
+            payload: json!({ "type": "window_info", "timestamp": 1, "data": { "title": "README.md - Code" } }),
+        })
+    }
+
+    fn fast_retries(max_retries: u32) -> WebhookConfig {
+        WebhookConfig {
+            max_retries,
+            retry: ReconnectPolicy { min_delay_ms: 10, max_delay_ms: 20, jitter: false, ..ReconnectPolicy::default() },
+            ..WebhookConfig::default()
+        }
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231 style known answer
+        let expected = "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8";
+        assert_eq!(signature("key", &[b"The quick brown fox jumps over the lazy dog"]), expected);
+        assert_eq!(signature("key", &[b"The quick brown fox ", b"jumps over the lazy dog"]), expected);
+    }
+
+    #[tokio::test]
+    async fn posts_signed_json_with_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let webhook = WebhookConfig {
+            headers: BTreeMap::from([("X-Api-Key".to_string(), "key-1".to_string())]),
+            signing_secret: Some("hook-secret".to_string()),
+            ..WebhookConfig::default()
+        };
+        let target = Target::new(&endpoint(listener.local_addr().unwrap(), webhook)).unwrap();
+        let server = tokio::spawn(serve(listener, vec![200]));
+
+        let outgoing = window_info();
+        assert!(target.send(&outgoing).await.is_ok());
+        let request = &server.await.unwrap()[0];
+
+        assert_eq!(request.path, "/hook");
+        assert_eq!(request.headers["content-type"], "application/json");
+        assert_eq!(request.headers["authorization"], "Bearer secret-token");
+        assert_eq!(request.headers["x-api-key"], "key-1");
+        let Outgoing::Json(entry) = outgoing else { unreachable!() };
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&request.body).unwrap(), entry.payload);
+        let expected = signature("hook-secret", &[&request.body]);
+        assert_eq!(request.headers[&SIGNATURE_HEADER.to_ascii_lowercase()], expected);
+    }
+
+    #[tokio::test]
+    async fn signs_artwork_meta_and_image() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let webhook = WebhookConfig { signing_secret: Some("hook-secret".to_string()), ..WebhookConfig::default() };
+        let target = Target::new(&endpoint(listener.local_addr().unwrap(), webhook)).unwrap();
+        let server = tokio::spawn(serve(listener, vec![200]));
+
+        let image = b"\x89PNG fake image".to_vec();
+        let outgoing = Outgoing::Artwork {
+            content_item_identifier: "song-1".to_string(),
+            hash: "abc".to_string(),
+            artwork_data: Arc::new(image.clone()),
+            mime_type: "image/png".to_string(),
+        };
+        assert!(target.send(&outgoing).await.is_ok());
+        let request = &server.await.unwrap()[0];
+
+        assert!(request.headers["content-type"].starts_with("multipart/form-data"));
+        let meta = UploadArtworkMetaMessage::new("song-1".to_string(), "abc".to_string(), "image/png".to_string());
+        let meta = serde_json::to_string(&meta).unwrap();
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains(&meta));
+        assert!(request.body.windows(image.len()).any(|window| window == image.as_slice()));
+        let expected = signature("hook-secret", &[meta.as_bytes(), &image]);
+        assert_eq!(request.headers[&SIGNATURE_HEADER.to_ascii_lowercase()], expected);
+    }
+
+    #[tokio::test]
+    async fn unsigned_without_secret() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = Target::new(&endpoint(listener.local_addr().unwrap(), WebhookConfig::default())).unwrap();
+        let server = tokio::spawn(serve(listener, vec![200]));
+
+        assert!(target.send(&window_info()).await.is_ok());
+        let request = &server.await.unwrap()[0];
+        assert!(!request.headers.contains_key(&SIGNATURE_HEADER.to_ascii_lowercase()));
+    }
+
+    #[tokio::test]
+    async fn retries_after_server_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = Target::new(&endpoint(listener.local_addr().unwrap(), fast_retries(3))).unwrap();
+        let server = tokio::spawn(serve(listener, vec![503, 500, 200]));
+
+        let result = target.send_with_retry("test", &window_info(), &fast_retries(3)).await;
+        assert!(result.is_ok());
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|request| request.body == requests[0].body));
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = Target::new(&endpoint(listener.local_addr().unwrap(), fast_retries(3))).unwrap();
+        let server = tokio::spawn(serve(listener, vec![400]));
+
+        let result = target.send_with_retry("test", &window_info(), &fast_retries(3)).await;
+        assert!(matches!(result, Err(Failure::Rejected(_))));
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_run_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = Target::new(&endpoint(listener.local_addr().unwrap(), fast_retries(1))).unwrap();
+        let server = tokio::spawn(serve(listener, vec![502, 502]));
+
+        let result = target.send_with_retry("test", &window_info(), &fast_retries(1)).await;
+        assert!(matches!(result, Err(Failure::Retryable(_))));
+        assert_eq!(server.await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,58 @@
+//! Minimal HTTP/1.1 server answering one request per connection
+//!
+//! Shared by the unit tests (`services::test_http`) and the integration tests (`common::http`).
+
+use std::collections::HashMap;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// A request as received, header names lowercased
+pub struct Request {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Status and extra headers of a response
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+impl From<u16> for Reply {
+    fn from(status: u16) -> Self {
+        Self { status, headers: Vec::new() }
+    }
+}
+
+/// Answer one request per connection with the next reply, returning what was received
+pub async fn serve<R: Into<Reply>>(listener: TcpListener, replies: Vec<R>) -> Vec<Request> {
+    let mut received = Vec::new();
+    for reply in replies {
+        let reply = reply.into();
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let path = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            stream.read_line(&mut line).await.unwrap();
+            let Some((name, value)) = line.trim_end().split_once(':') else { break };
+            headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+        }
+        let length = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+        received.push(Request { path, headers, body });
+
+        let mut response = format!("HTTP/1.1 {} Test\r\nContent-Length: 2\r\nConnection: close\r\n", reply.status);
+        for (name, value) in &reply.headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n{}");
+        stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+    }
+    received
+}
@@ -0,0 +1,112 @@
+//! Drives the reporter through the mock platform against a local WebSocket server
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use shikenmatrix::platform::mock::MockProvider;
+use shikenmatrix::services::queue::QueueConfig;
+use shikenmatrix::services::artwork::ArtworkCacheConfig;
+use shikenmatrix::{Reporter, ReporterConfig};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/timeline.toml");
+
+/// Messages the server received, `hello` first, until `done` says enough
+///
+/// `script` is sent right after `welcome`.
+async fn serve_one(listener: TcpListener, script: &[Value], done: impl Fn(&[Value]) -> bool) -> Vec<Value> {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+    let mut received = Vec::new();
+    while let Some(frame) = socket.next().await {
+        let Ok(Message::Text(text)) = frame else { continue };
+        let msg: Value = serde_json::from_str(&text).unwrap();
+        if msg["type"] == "hello" {
+            let welcome = json!({ "type": "welcome", "protocol_version": 1, "features": { "artwork_upload": false } });
+            socket.send(Message::Text(welcome.to_string().into())).await.unwrap();
+            for msg in script {
+                socket.send(Message::Text(msg.to_string().into())).await.unwrap();
+            }
+        }
+        received.push(msg);
+        if done(&received) {
+            break;
+        }
+    }
+    received
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_the_timeline() {
+    // Read by the monitor thread on every tick
+    std::env::set_var("ENABLE_MEDIA_REPORTING", "1");
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = ReporterConfig {
+        enabled: true,
+        ws_url: format!("ws://{}/ws", listener.local_addr().unwrap()),
+        token: "test-token".to_string(),
+        enable_media_reporting: true,
+        queue: QueueConfig { enabled: false, ..QueueConfig::default() },
+        artwork_cache: ArtworkCacheConfig { enabled: false, ..ArtworkCacheConfig::default() },
+        ..ReporterConfig::default()
+    };
+    let provider = MockProvider::from_file(FIXTURE).unwrap();
+    let reporter = Reporter::new(config, Box::new(provider));
+
+    let saw_video = |received: &[Value]| {
+        received.iter().any(|msg| msg["type"] == "media_playback" && msg["metadata"]["title"] == "Video")
+    };
+    let received = tokio::time::timeout(Duration::from_secs(10), serve_one(listener, &[], saw_video)).await;
+    assert!(reporter.stop(Duration::from_secs(5)));
+    let received = received.expect("timeline not reported in time");
+
+    assert_eq!(received[0]["type"], "hello");
+    let windows: Vec<&Value> = received.iter().filter(|msg| msg["type"] == "window_info").collect();
+    let titles: Vec<&str> = windows.iter().filter_map(|msg| msg["data"]["title"].as_str()).collect();
+    assert_eq!(titles, ["README.md - Code", "YouTube - Firefox"]);
+    assert_eq!(windows[0]["data"]["process_name"], "code");
+    assert_eq!(windows[1]["data"]["pid"], 200);
+
+    let media: Vec<&Value> = received.iter().filter(|msg| msg["type"] == "media_playback").collect();
+    let song = media.first().unwrap();
+    assert_eq!(song["metadata"]["title"], "Song");
+    assert_eq!(song["metadata"]["artist"], "Artist");
+    assert_eq!(song["metadata"]["content_item_identifier"], "song-1");
+    assert_eq!(song["playback_state"]["playing"], true);
+    let video = media.last().unwrap();
+    assert_eq!(video["metadata"]["content_item_identifier"], "video-1");
+    assert_eq!(video["playback_state"]["playing"], false);
+    assert_eq!(video["playback_state"]["elapsed_time"], 30.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn acknowledges_commands() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = ReporterConfig {
+        enabled: true,
+        ws_url: format!("ws://{}/ws", listener.local_addr().unwrap()),
+        queue: QueueConfig { enabled: false, ..QueueConfig::default() },
+        artwork_cache: ArtworkCacheConfig { enabled: false, ..ArtworkCacheConfig::default() },
+        ..ReporterConfig::default()
+    };
+    let reporter = Reporter::new(config, Box::new(MockProvider::from_file(FIXTURE).unwrap()));
+
+    let script = [
+        json!({ "type": "command", "id": "1", "command": "set_poll_interval", "interval_ms": 500 }),
+        json!({ "type": "command", "id": "2", "command": "set_poll_interval", "interval_ms": 100 }),
+        json!({ "type": "command", "id": "3", "command": "reboot" }),
+    ];
+    let answered = |received: &[Value]| received.iter().filter(|msg| msg["type"] == "command_result").count() == 3;
+    let received = tokio::time::timeout(Duration::from_secs(10), serve_one(listener, &script, answered)).await;
+    assert!(reporter.stop(Duration::from_secs(5)));
+    let received = received.expect("commands not answered in time");
+
+    let results: Vec<&Value> = received.iter().filter(|msg| msg["type"] == "command_result").collect();
+    assert_eq!(*results[0], json!({ "type": "command_result", "id": "1", "ok": true }));
+    assert_eq!(*results[1], json!({ "type": "command_result", "id": "2", "ok": false, "error": "interval_ms must be between 250 and 60000" }));
+    assert_eq!(results[2]["id"], "3");
+    assert_eq!(results[2]["ok"], false);
+    assert!(results[2]["error"].as_str().unwrap().contains("reboot"), "{}", results[2]);
+}
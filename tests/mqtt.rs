@@ -0,0 +1,113 @@
+//! MQTT transport against a real broker
+//!
+//! Needs a broker without authentication, e.g. `mosquitto -p 1883`; run with
+//! `SHIKENMATRIX_TEST_MQTT=mqtt://127.0.0.1:1883 cargo test --test mqtt -- --ignored`.
+
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use serde_json::Value;
+use shikenmatrix::platform::mock::MockProvider;
+use shikenmatrix::services::artwork::ArtworkCacheConfig;
+use shikenmatrix::services::mqtt::{MqttConfig, MqttVersion};
+use shikenmatrix::services::queue::QueueConfig;
+use shikenmatrix::services::reporter::Transport;
+use shikenmatrix::{Reporter, ReporterConfig};
+use std::collections::HashMap;
+use std::time::Duration;
+use url::Url;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/timeline.toml");
+
+fn broker_url() -> String {
+    std::env::var("SHIKENMATRIX_TEST_MQTT").unwrap_or_else(|_| "mqtt://127.0.0.1:1883".to_string())
+}
+
+/// Subscriber on `<prefix>/#`, always speaking 3.1.1
+async fn subscribe(prefix: &str) -> (AsyncClient, EventLoop) {
+    let url = Url::parse(&broker_url()).unwrap();
+    let id = format!("shikenmatrix-test-{:08x}", fastrand::u32(..));
+    let options = MqttOptions::new(id, url.host_str().unwrap(), url.port().unwrap_or(1883));
+    let (client, events) = AsyncClient::new(options, 16);
+    client.subscribe(format!("{}/#", prefix), QoS::AtLeastOnce).await.unwrap();
+    (client, events)
+}
+
+/// Publishes until `done` holds, keyed by topic: (payload, retained)
+async fn collect(events: &mut EventLoop, done: impl Fn(&HashMap<String, (String, bool)>) -> bool) -> HashMap<String, (String, bool)> {
+    let mut received = HashMap::new();
+    let wait = async {
+        while !done(&received) {
+            if let Event::Incoming(Packet::Publish(publish)) = events.poll().await.expect("broker connection lost") {
+                let payload = String::from_utf8_lossy(&publish.payload).to_string();
+                received.insert(publish.topic, (payload, publish.retain));
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), wait).await.expect("broker didn't deliver in time");
+    received
+}
+
+fn title(received: &HashMap<String, (String, bool)>, topic: &str, pointer: &str) -> Option<String> {
+    let (payload, _) = received.get(topic)?;
+    let value: Value = serde_json::from_str(payload).ok()?;
+    value.pointer(pointer)?.as_str().map(str::to_string)
+}
+
+async fn publishes_retained_state(version: MqttVersion) {
+    // Read by the monitor thread on every tick
+    std::env::set_var("ENABLE_MEDIA_REPORTING", "1");
+
+    let prefix = format!("shikenmatrix-test/{:08x}", fastrand::u32(..));
+    let config = ReporterConfig {
+        enabled: true,
+        ws_url: broker_url(),
+        transport: Transport::Mqtt,
+        enable_media_reporting: true,
+        queue: QueueConfig { enabled: false, ..QueueConfig::default() },
+        artwork_cache: ArtworkCacheConfig { enabled: false, ..ArtworkCacheConfig::default() },
+        mqtt: MqttConfig { version, topic_prefix: prefix.clone(), ..MqttConfig::default() },
+        ..ReporterConfig::default()
+    };
+    let reporter = Reporter::new(config, Box::new(MockProvider::from_file(FIXTURE).unwrap()));
+
+    // The timeline switches to the video after 1.5s
+    let (window, media, status) = (format!("{}/window", prefix), format!("{}/media", prefix), format!("{}/status", prefix));
+    let (_live, mut live_events) = subscribe(&prefix).await;
+    collect(&mut live_events, |received| {
+        title(received, &media, "/metadata/title").as_deref() == Some("Video")
+            && title(received, &window, "/data/title").as_deref() == Some("YouTube - Firefox")
+    })
+    .await;
+
+    // A late subscriber gets the latest state from the retained messages
+    let (late, mut late_events) = subscribe(&prefix).await;
+    let retained = collect(&mut late_events, |received| received.len() == 3).await;
+    assert_eq!(retained[&status], (String::from("online"), true));
+    assert!(retained[&window].1 && retained[&media].1);
+    assert_eq!(title(&retained, &window, "/data/title").as_deref(), Some("YouTube - Firefox"));
+    assert_eq!(title(&retained, &media, "/metadata/content_item_identifier").as_deref(), Some("video-1"));
+
+    // Stopping drops the connection without DISCONNECT, so the broker publishes the last will
+    assert!(reporter.stop(Duration::from_secs(5)));
+    let will = collect(&mut late_events, |received| received.get(&status).is_some_and(|(payload, _)| payload == "offline")).await;
+    assert_eq!(will[&status].0, "offline");
+
+    // Leave nothing retained behind
+    for topic in [&window, &media, &status] {
+        late.publish(topic, QoS::AtLeastOnce, true, Vec::new()).await.unwrap();
+    }
+    late.disconnect().await.unwrap();
+    let flush = async { while late_events.poll().await.is_ok() {} };
+    let _ = tokio::time::timeout(Duration::from_secs(2), flush).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs an MQTT broker, see SHIKENMATRIX_TEST_MQTT"]
+async fn v311_publishes_retained_state() {
+    publishes_retained_state(MqttVersion::V311).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs an MQTT broker, see SHIKENMATRIX_TEST_MQTT"]
+async fn v5_publishes_retained_state() {
+    publishes_retained_state(MqttVersion::V5).await;
+}
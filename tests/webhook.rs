@@ -0,0 +1,61 @@
+//! Webhook delivery through the mock platform against a local HTTP server
+
+mod common;
+
+use common::http::serve;
+use serde_json::Value;
+use shikenmatrix::platform::mock::{MockProvider, MockWindow, Timeline, TimelineEvent};
+use shikenmatrix::services::artwork::ArtworkCacheConfig;
+use shikenmatrix::services::queue::QueueConfig;
+use shikenmatrix::services::reconnect::ReconnectPolicy;
+use shikenmatrix::services::reporter::Transport;
+use shikenmatrix::services::webhook::WebhookConfig;
+use shikenmatrix::{Reporter, ReporterConfig};
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retries_without_new_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = ReporterConfig {
+        enabled: true,
+        ws_url: format!("http://{}/hook", listener.local_addr().unwrap()),
+        token: "test-token".to_string(),
+        proxy_url: Some("direct".to_string()),
+        transport: Transport::Webhook,
+        // Nothing is parked, the retry has to come from the backoff timer
+        queue: QueueConfig { enabled: false, ..QueueConfig::default() },
+        artwork_cache: ArtworkCacheConfig { enabled: false, ..ArtworkCacheConfig::default() },
+        reconnect: ReconnectPolicy { min_delay_ms: 100, max_delay_ms: 200, jitter: false, ..ReconnectPolicy::default() },
+        webhook: WebhookConfig { max_retries: 0, min_interval_ms: 50, ..WebhookConfig::default() },
+        ..ReporterConfig::default()
+    };
+    // A single window that never changes, so no further messages are produced
+    let timeline = Timeline {
+        repeat_after_ms: None,
+        events: vec![TimelineEvent {
+            at_ms: 0,
+            window: Some(MockWindow {
+                title: "README.md - Code".to_string(),
+                process_name: "code".to_string(),
+                pid: 100,
+                ..MockWindow::default()
+            }),
+            media: None,
+        }],
+    };
+    let provider = MockProvider::new(timeline, Path::new(".")).unwrap();
+    let reporter = Reporter::new(config, Box::new(provider));
+
+    let received = tokio::time::timeout(Duration::from_secs(3), serve(listener, vec![503, 502, 200])).await;
+    assert!(reporter.stop(Duration::from_secs(5)));
+    let received = received.expect("failed delivery was not retried");
+
+    assert_eq!(received.len(), 3);
+    for request in &received {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["type"], "window_info");
+        assert_eq!(body["data"]["title"], "README.md - Code");
+    }
+}